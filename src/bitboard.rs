@@ -0,0 +1,381 @@
+use super::board::Color;
+
+/// Set of squares, one bit per square.
+///
+/// Square `8 * (row - 1) + (file - 1)` is bit number
+/// `square`, so a1 is bit 0, h1 is bit 7 and h8 is bit 63.
+pub type Bitboard = u64;
+
+pub const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+pub const RANK_1: Bitboard = 0xFF;
+pub const RANK_2: Bitboard = RANK_1 << 8;
+pub const RANK_7: Bitboard = RANK_1 << 48;
+pub const RANK_8: Bitboard = RANK_1 << 56;
+
+/// Bitboard with only square `square` set.
+pub fn square_bb(square: u8) -> Bitboard {
+    1u64 << square
+}
+
+/// Iterator over the squares of a bitboard, from a1 to h8.
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let square = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+
+        Some(square)
+    }
+}
+
+/// Squares of bitboard `bb`.
+pub fn squares(bb: Bitboard) -> Squares {
+    Squares(bb)
+}
+
+fn offset(square: u8, row_delta: i32, file_delta: i32) -> Option<u8> {
+    let row = (square / 8) as i32 + row_delta;
+    let file = (square % 8) as i32 + file_delta;
+
+    match (row, file) {
+        (0..=7, 0..=7) => Some((8 * row + file) as u8),
+        _ => None,
+    }
+}
+
+fn leaper_attacks(square: u8, deltas: &[(i32, i32)]) -> Bitboard {
+    deltas
+        .iter()
+        .filter_map(|(row_delta, file_delta)| offset(square, *row_delta, *file_delta))
+        .fold(0, |bb, target| bb | square_bb(target))
+}
+
+const KNIGHT_DELTAS: [(i32, i32); 8] = [
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+];
+
+const KING_DELTAS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+lazy_static! {
+    static ref KNIGHT_ATTACKS: [Bitboard; 64] = {
+        let mut table = [0; 64];
+        for (square, attacks) in table.iter_mut().enumerate() {
+            *attacks = leaper_attacks(square as u8, &KNIGHT_DELTAS);
+        }
+        table
+    };
+    static ref KING_ATTACKS: [Bitboard; 64] = {
+        let mut table = [0; 64];
+        for (square, attacks) in table.iter_mut().enumerate() {
+            *attacks = leaper_attacks(square as u8, &KING_DELTAS);
+        }
+        table
+    };
+    static ref PAWN_ATTACKS: [[Bitboard; 64]; 2] = {
+        let mut table = [[0; 64]; 2];
+        for (square, attacks) in table[Color::White.index()].iter_mut().enumerate() {
+            *attacks = leaper_attacks(square as u8, &[(1, -1), (1, 1)]);
+        }
+        for (square, attacks) in table[Color::Black.index()].iter_mut().enumerate() {
+            *attacks = leaper_attacks(square as u8, &[(-1, -1), (-1, 1)]);
+        }
+        table
+    };
+}
+
+/// Squares attacked by a knight standing on `square`.
+pub fn knight_attacks(square: u8) -> Bitboard {
+    KNIGHT_ATTACKS[square as usize]
+}
+
+/// Squares attacked by a king standing on `square`.
+pub fn king_attacks(square: u8) -> Bitboard {
+    KING_ATTACKS[square as usize]
+}
+
+/// Squares attacked by a pawn of color `color` standing on `square`.
+pub fn pawn_attacks(color: &Color, square: u8) -> Bitboard {
+    PAWN_ATTACKS[color.index()][square as usize]
+}
+
+fn slider_attacks(square: u8, occupied: Bitboard, directions: &[(i32, i32)]) -> Bitboard {
+    let mut attacks: Bitboard = 0;
+
+    for (row_delta, file_delta) in directions.iter() {
+        let mut current = square;
+        while let Some(target) = offset(current, *row_delta, *file_delta) {
+            attacks |= square_bb(target);
+            if occupied & square_bb(target) != 0 {
+                break;
+            }
+            current = target;
+        }
+    }
+
+    attacks
+}
+
+/// Squares whose occupancy matters for a slider on `square`.
+///
+/// The last square of every ray is left out, because
+/// a piece standing there does not block anything.
+fn relevant_occupancy(square: u8, directions: &[(i32, i32)]) -> Bitboard {
+    let mut mask: Bitboard = 0;
+
+    for (row_delta, file_delta) in directions.iter() {
+        let mut current = square;
+        while let Some(target) = offset(current, *row_delta, *file_delta) {
+            if offset(target, *row_delta, *file_delta).is_none() {
+                break;
+            }
+            mask |= square_bb(target);
+            current = target;
+        }
+    }
+
+    mask
+}
+
+/// Xorshift generator used for finding magic numbers.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Random number with few bits set, which makes
+    /// a good magic candidate.
+    fn next_sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Magic bitboard lookup table for one kind of slider.
+struct SliderTable {
+    magics: [Magic; 64],
+    attacks: Vec<Bitboard>,
+}
+
+impl SliderTable {
+    fn new(directions: &[(i32, i32)]) -> Self {
+        let mut random = Xorshift(0x9E37_79B9_7F4A_7C15);
+        let mut magics = [Magic::default(); 64];
+        let mut attacks: Vec<Bitboard> = Vec::new();
+
+        for (square, magic) in magics.iter_mut().enumerate() {
+            let square = square as u8;
+            let mask = relevant_occupancy(square, directions);
+            let bits = mask.count_ones();
+
+            // Wszystkie podzbiory maski (Carry-Rippler)
+            // razem z odpowiadającymi im atakami.
+            let mut occupancies: Vec<Bitboard> = Vec::with_capacity(1 << bits);
+            let mut subset: Bitboard = 0;
+            loop {
+                occupancies.push(subset);
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+            let reference: Vec<Bitboard> = occupancies
+                .iter()
+                .map(|occupied| slider_attacks(square, *occupied, directions))
+                .collect();
+
+            *magic = Magic {
+                mask,
+                magic: 0,
+                shift: 64 - bits,
+                offset: attacks.len(),
+            };
+            attacks.resize(attacks.len() + (1 << bits), 0);
+
+            // Szukamy liczby, dla której różne zbiory ataków
+            // nie trafiają w ten sam indeks.
+            let mut used = vec![0u32; 1 << bits];
+            for attempt in 1u32.. {
+                magic.magic = random.next_sparse();
+                if (mask.wrapping_mul(magic.magic) >> 56).count_ones() < 6 {
+                    continue;
+                }
+
+                let collision =
+                    occupancies
+                        .iter()
+                        .zip(reference.iter())
+                        .any(|(occupied, expected)| {
+                            let index = magic.index(*occupied);
+                            if used[index - magic.offset] != attempt {
+                                used[index - magic.offset] = attempt;
+                                attacks[index] = *expected;
+                                false
+                            } else {
+                                attacks[index] != *expected
+                            }
+                        });
+
+                if !collision {
+                    break;
+                }
+            }
+        }
+
+        SliderTable { magics, attacks }
+    }
+
+    fn attacks(&self, square: u8, occupied: Bitboard) -> Bitboard {
+        self.attacks[self.magics[square as usize].index(occupied)]
+    }
+}
+
+lazy_static! {
+    static ref ROOK_TABLE: SliderTable = SliderTable::new(&ROOK_DIRECTIONS);
+    static ref BISHOP_TABLE: SliderTable = SliderTable::new(&BISHOP_DIRECTIONS);
+}
+
+/// Squares attacked by a rook standing on `square`
+/// when squares `occupied` are taken.
+pub fn rook_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    ROOK_TABLE.attacks(square, occupied)
+}
+
+/// Squares attacked by a bishop standing on `square`
+/// when squares `occupied` are taken.
+pub fn bishop_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    BISHOP_TABLE.attacks(square, occupied)
+}
+
+/// Squares attacked by a queen standing on `square`
+/// when squares `occupied` are taken.
+pub fn queen_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+lazy_static! {
+    static ref BETWEEN: Vec<[Bitboard; 64]> = {
+        let mut table = vec![[0; 64]; 64];
+        for a in 0..64u8 {
+            for b in 0..64u8 {
+                // Pola między a i b to te, które widać
+                // z obu pól naraz, idąc po jednej linii.
+                table[a as usize][b as usize] = if rook_attacks(a, 0) & square_bb(b) != 0 {
+                    rook_attacks(a, square_bb(b)) & rook_attacks(b, square_bb(a))
+                } else if bishop_attacks(a, 0) & square_bb(b) != 0 {
+                    bishop_attacks(a, square_bb(b)) & bishop_attacks(b, square_bb(a))
+                } else {
+                    0
+                };
+            }
+        }
+        table
+    };
+}
+
+/// Squares strictly between `a` and `b` when they lie on
+/// one rank, file or diagonal, otherwise no squares.
+pub fn between(a: u8, b: u8) -> Bitboard {
+    BETWEEN[a as usize][b as usize]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_leaper_attacks() {
+        // Skoczek w rogu a1 atakuje b3 i c2.
+        assert_eq!(knight_attacks(0), square_bb(17) | square_bb(10));
+        assert_eq!(king_attacks(0).count_ones(), 3);
+        assert_eq!(king_attacks(27).count_ones(), 8);
+        assert_eq!(pawn_attacks(&Color::White, 8), square_bb(17));
+        assert_eq!(pawn_attacks(&Color::Black, 55), square_bb(46));
+    }
+
+    #[test]
+    fn test_slider_attacks() {
+        assert_eq!(rook_attacks(0, 0), (FILE_A | RANK_1) & !square_bb(0));
+        // Wieża na a1, blokada na a3 i c1.
+        let occupied = square_bb(16) | square_bb(2);
+        assert_eq!(
+            rook_attacks(0, occupied),
+            square_bb(8) | square_bb(16) | square_bb(1) | square_bb(2)
+        );
+        assert_eq!(bishop_attacks(0, 0).count_ones(), 7);
+        assert_eq!(queen_attacks(27, 0).count_ones(), 27);
+    }
+
+    #[test]
+    fn test_between() {
+        // a1 i d4: b2 i c3.
+        assert_eq!(between(0, 27), square_bb(9) | square_bb(18));
+        assert_eq!(between(27, 0), square_bb(9) | square_bb(18));
+        // a1 i a8: cała kolumna poza końcami.
+        assert_eq!(between(0, 56), FILE_A & !square_bb(0) & !square_bb(56));
+        assert_eq!(between(0, 1), 0);
+        // a1 i b3 nie leżą na jednej linii.
+        assert_eq!(between(0, 17), 0);
+    }
+
+    #[test]
+    fn test_magic_tables() {
+        let mut random = Xorshift(1);
+        for square in 0..64 {
+            for _ in 0..200 {
+                let occupied = random.next_sparse() | random.next_sparse();
+                assert_eq!(
+                    rook_attacks(square, occupied),
+                    slider_attacks(square, occupied, &ROOK_DIRECTIONS)
+                );
+                assert_eq!(
+                    bishop_attacks(square, occupied),
+                    slider_attacks(square, occupied, &BISHOP_DIRECTIONS)
+                );
+            }
+        }
+    }
+}
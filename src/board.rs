@@ -1,7 +1,24 @@
-use std::cmp;
 use std::fmt;
 
+use super::bitboard::bishop_attacks;
+use super::bitboard::king_attacks;
+use super::bitboard::knight_attacks;
+use super::bitboard::pawn_attacks;
+use super::bitboard::rook_attacks;
+use super::bitboard::square_bb;
+use super::bitboard::squares;
+use super::bitboard::Bitboard;
+use super::bitboard::FILE_A;
+use super::bitboard::RANK_1;
+use super::bitboard::RANK_8;
 use super::book::Move;
+use super::error::FENError;
+use super::error::FieldError;
+use super::error::MoveError;
+use super::moves::is_check;
+use super::moves::move_from_uci;
+use super::moves::player_moves;
+use super::moves::MoveList;
 use super::polyglot_data::RANDOM_CASTLE;
 use super::polyglot_data::RANDOM_EN_PASSANT;
 use super::polyglot_data::RANDOM_PIECE;
@@ -18,6 +35,20 @@ pub enum KindOfPiece {
     King,
 }
 
+impl KindOfPiece {
+    /// Index of this kind of piece in per-kind tables.
+    pub fn index(&self) -> usize {
+        match self {
+            KindOfPiece::Pawn => 0,
+            KindOfPiece::Knight => 1,
+            KindOfPiece::Bishop => 2,
+            KindOfPiece::Rook => 3,
+            KindOfPiece::Queen => 4,
+            KindOfPiece::King => 5,
+        }
+    }
+}
+
 impl fmt::Display for KindOfPiece {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -48,14 +79,48 @@ impl Color {
             Color::Black => Color::White,
         }
     }
+
+    /// Index of this color in per-color tables.
+    pub fn index(&self) -> usize {
+        match self {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Castle {
     Short,
     Long,
 }
 
+/// Index of the castling right of player of color `color` on side `side`,
+/// in the order of the Polyglot keys.
+fn castle_index(color: &Color, side: &Castle) -> usize {
+    match (*color, *side) {
+        (Color::White, Castle::Short) => 0,
+        (Color::White, Castle::Long) => 1,
+        (Color::Black, Castle::Short) => 2,
+        (Color::Black, Castle::Long) => 3,
+    }
+}
+
+/// Squares the king and the rook of player of color `color`
+/// land on after castling on side `side`. In Chess960 they are
+/// the same as in standard chess, wherever the pieces started.
+pub fn castle_destinations(color: &Color, side: &Castle) -> (u8, u8) {
+    let rank = match color {
+        Color::White => 0,
+        Color::Black => 56,
+    };
+
+    match side {
+        Castle::Short => (rank + 6, rank + 5),
+        Castle::Long => (rank + 2, rank + 3),
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Piece {
     pub kind_of_piece: KindOfPiece,
@@ -113,14 +178,7 @@ const BLACK_PAWN: Piece = Piece {
 
 impl Piece {
     pub fn code(&self) -> u8 {
-        let code = match self.kind_of_piece {
-            KindOfPiece::Pawn => 0,
-            KindOfPiece::Knight => 1,
-            KindOfPiece::Bishop => 2,
-            KindOfPiece::Rook => 3,
-            KindOfPiece::Queen => 4,
-            KindOfPiece::King => 5,
-        };
+        let code = self.kind_of_piece.index() as u8;
 
         match self.color {
             Color::Black => code * 2,
@@ -129,96 +187,144 @@ impl Piece {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+/// State lost by `Board::make_move`, needed to take the move back.
+#[derive(Copy, Clone, Debug)]
+pub struct UndoInfo {
+    captured: Option<(u8, Piece)>,
+    castling: Option<Castle>,
+    castle: [bool; 4],
+    en_passant: [bool; 8],
+    halfmove_clock: u16,
+    hash: u64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct Board {
-    pub fields: [[Option<Piece>; 8]; 8],
+    squares: [Option<Piece>; 64],
+    pieces: [Bitboard; 6],
+    colors: [Bitboard; 2],
     castle: [bool; 4],
+    /// File of the rook taking part in every castling,
+    /// which in Chess960 does not have to be a or h.
+    castle_rook_files: [u8; 4],
     en_passant: [bool; 8],
     turn: Color,
+    hash: u64,
+    /// Plies since the last capture or pawn move.
+    halfmove_clock: u16,
+    /// Starts at 1 and grows after every move of black.
+    fullmove_number: u16,
+    /// Hashes of the positions before every move made
+    /// with `make_move`, the latest last.
+    hash_history: Vec<u64>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Board {
     /// Constructor.
     pub fn new() -> Self {
+        const BACK_RANK: [KindOfPiece; 8] = [
+            KindOfPiece::Rook,
+            KindOfPiece::Knight,
+            KindOfPiece::Bishop,
+            KindOfPiece::Queen,
+            KindOfPiece::King,
+            KindOfPiece::Bishop,
+            KindOfPiece::Knight,
+            KindOfPiece::Rook,
+        ];
+
+        let mut board = Board::empty();
+        for (file, kind_of_piece) in BACK_RANK.into_iter().enumerate() {
+            let file = file as u8;
+
+            board.put_piece(
+                file,
+                Piece {
+                    kind_of_piece,
+                    color: Color::White,
+                },
+            );
+            board.put_piece(8 + file, WHITE_PAWN);
+            board.put_piece(48 + file, BLACK_PAWN);
+            board.put_piece(
+                56 + file,
+                Piece {
+                    kind_of_piece,
+                    color: Color::Black,
+                },
+            );
+        }
+        board.castle = [true, true, true, true];
+        board.hash = board.compute_hash();
+
+        board
+    }
+
+    fn empty() -> Self {
         Board {
-            fields: [
-                [
-                    Some(WHITE_ROOK),
-                    Some(WHITE_KNIGHT),
-                    Some(WHITE_BISHOP),
-                    Some(WHITE_QUEEN),
-                    Some(WHITE_KING),
-                    Some(WHITE_BISHOP),
-                    Some(WHITE_KNIGHT),
-                    Some(WHITE_ROOK),
-                ],
-                [
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                    Some(WHITE_PAWN),
-                ],
-                [None, None, None, None, None, None, None, None],
-                [None, None, None, None, None, None, None, None],
-                [None, None, None, None, None, None, None, None],
-                [None, None, None, None, None, None, None, None],
-                [
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                    Some(BLACK_PAWN),
-                ],
-                [
-                    Some(BLACK_ROOK),
-                    Some(BLACK_KNIGHT),
-                    Some(BLACK_BISHOP),
-                    Some(BLACK_QUEEN),
-                    Some(BLACK_KING),
-                    Some(BLACK_BISHOP),
-                    Some(BLACK_KNIGHT),
-                    Some(BLACK_ROOK),
-                ],
-            ],
-            castle: [true, true, true, true],
-            en_passant: [false, false, false, false, false, false, false, false],
+            squares: [None; 64],
+            pieces: [0; 6],
+            colors: [0; 2],
+            castle: [false; 4],
+            castle_rook_files: [7, 0, 7, 0],
+            en_passant: [false; 8],
             turn: Color::White,
+            hash: RANDOM_TURN[0],
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash_history: Vec::new(),
         }
     }
 
-    /// Zobrist hash of the board.
-    pub fn hash(&self) -> u64 {
-        let mut key: u64 = 0;
+    fn put_piece(&mut self, square: u8, piece: Piece) {
+        self.squares[square as usize] = Some(piece);
+        self.pieces[piece.kind_of_piece.index()] |= square_bb(square);
+        self.colors[piece.color.index()] |= square_bb(square);
+        self.hash ^= field_hash(&piece, &Field::from_square(square));
+    }
 
-        for r in 0..self.fields.len() {
-            for c in 0..self.fields[r].len() {
-                let field = Field {
-                    row: r as u8,
-                    file: c as u8,
-                };
+    fn remove_piece(&mut self, square: u8) -> Option<Piece> {
+        let piece = self.squares[square as usize].take()?;
+        self.pieces[piece.kind_of_piece.index()] &= !square_bb(square);
+        self.colors[piece.color.index()] &= !square_bb(square);
+        self.hash ^= field_hash(&piece, &Field::from_square(square));
 
-                if let Some(ref piece) = self.fields[r][c] {
-                    key ^= field_hash(piece, &field);
-                }
-            }
-        }
+        Some(piece)
+    }
+
+    /// Part of the hash coming from castling rights
+    /// and en passant possibilities.
+    fn rights_hash(&self) -> u64 {
+        let mut key: u64 = 0;
 
-        for i in 0..4 {
+        for (i, random) in RANDOM_CASTLE.iter().enumerate() {
             if self.castle[i] {
-                key ^= RANDOM_CASTLE[i];
+                key ^= random;
             }
         }
 
-        for i in 0..8 {
+        for (i, random) in RANDOM_EN_PASSANT.iter().enumerate() {
             if self.en_passant[i] {
-                key ^= RANDOM_EN_PASSANT[i];
+                key ^= random;
+            }
+        }
+
+        key
+    }
+
+    /// Polyglot Zobrist hash computed from scratch.
+    fn compute_hash(&self) -> u64 {
+        let mut key: u64 = self.rights_hash();
+
+        for square in squares(self.occupied()) {
+            if let Some(ref piece) = self.squares[square as usize] {
+                key ^= field_hash(piece, &Field::from_square(square));
             }
         }
 
@@ -228,13 +334,93 @@ impl Board {
         }
     }
 
+    /// Zobrist hash of the board.
+    ///
+    /// The hash is kept up to date by every move,
+    /// so this is only a lookup.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
     /// Can player of color `color` castle on side `side`.
     pub fn can_castle(&self, color: &Color, side: &Castle) -> bool {
-        match (*color, *side) {
-            (Color::White, Castle::Short) => self.castle[0],
-            (Color::White, Castle::Long) => self.castle[1],
-            (Color::Black, Castle::Short) => self.castle[2],
-            (Color::Black, Castle::Long) => self.castle[3],
+        self.castle[castle_index(color, side)]
+    }
+
+    /// File, counted from 0, of the rook with which player
+    /// of color `color` castles on side `side`.
+    pub fn castle_rook_file(&self, color: &Color, side: &Castle) -> u8 {
+        self.castle_rook_files[castle_index(color, side)]
+    }
+
+    /// Square of the rook taking part in castling number `index`.
+    fn castle_rook_square(&self, index: usize) -> u8 {
+        let rank = if index < 2 { 0 } else { 56 };
+
+        rank + self.castle_rook_files[index]
+    }
+
+    /// Side of castling if `move` is one.
+    ///
+    /// Castling is encoded as the king capturing its own rook,
+    /// like in Polyglot books and in UCI with `UCI_Chess960`.
+    pub fn castling_side(&self, r#move: &Move) -> Option<Castle> {
+        let from = r#move.from_square();
+        let to = r#move.to_square();
+
+        match (self.squares[from as usize], self.squares[to as usize]) {
+            (
+                Some(Piece {
+                    kind_of_piece: KindOfPiece::King,
+                    color,
+                }),
+                Some(Piece {
+                    kind_of_piece: KindOfPiece::Rook,
+                    color: rook_color,
+                }),
+            ) if color == rook_color => {
+                if to > from {
+                    Some(Castle::Short)
+                } else {
+                    Some(Castle::Long)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Move `move` with castling written in standard notation,
+    /// as the king moving two files, encoded as the king
+    /// capturing its own rook. Other moves are returned as they are.
+    pub fn castling_move(&self, r#move: &Move) -> Move {
+        let from = r#move.from_square();
+        let to = r#move.to_square();
+        let color = match self.squares[from as usize] {
+            Some(Piece {
+                kind_of_piece: KindOfPiece::King,
+                color,
+            }) => color,
+            _ => return *r#move,
+        };
+
+        let side = match to as i8 - from as i8 {
+            2 => Castle::Short,
+            -2 => Castle::Long,
+            _ => return *r#move,
+        };
+        if castle_destinations(&color, &side).0 != to || self.castling_side(r#move).is_some() {
+            return *r#move;
+        }
+
+        let rook = self.castle_rook_square(castle_index(&color, &side));
+        match self.squares[rook as usize] {
+            Some(Piece {
+                kind_of_piece: KindOfPiece::Rook,
+                color: rook_color,
+            }) if rook_color == color => {
+                Move::build(Field::from_square(from), Field::from_square(rook), None).unwrap()
+            }
+            _ => *r#move,
         }
     }
 
@@ -243,181 +429,488 @@ impl Board {
         self.en_passant[(file - 1) as usize]
     }
 
+    /// Square a pawn of the player on move lands on
+    /// when capturing en passant, if any.
+    pub fn en_passant_square(&self) -> Option<u8> {
+        let file = self.en_passant.iter().position(|flag| *flag)? as u8;
+
+        match self.turn {
+            Color::White => Some(40 + file),
+            Color::Black => Some(16 + file),
+        }
+    }
+
     /// Apply move without performing checks.
+    ///
+    /// Castling may be given as the king capturing its own rook
+    /// or, as in standard chess, as the king moving two files.
     pub fn apply_unchecked(&mut self, r#move: &Move) {
-        let field_content =
-            self.fields[(r#move.from_row() - 1) as usize][(r#move.from_file_number() - 1) as usize];
-
-        self.fields[(r#move.from_row() - 1) as usize][(r#move.from_file_number() - 1) as usize] =
-            None;
+        let r#move = self.castling_move(r#move);
+        self.apply(&r#move);
+    }
 
-        if let Some(piece_kind) = r#move.promotion() {
-            self.fields[(r#move.to_row() - 1) as usize][(r#move.to_file_number() - 1) as usize] =
-                Some(Piece {
-                    kind_of_piece: piece_kind,
-                    color: if let Some(piece) = field_content {
-                        piece.color
-                    } else {
-                        panic!("Failed to apply move.")
-                    },
-                });
+    /// Make move `move` and pass the turn to the other player.
+    /// Castling is accepted in both encodings, as in `apply_unchecked`.
+    ///
+    /// The returned record lets `unmake_move` restore
+    /// the board to what it was before the move.
+    pub fn make_move(&mut self, r#move: &Move) -> UndoInfo {
+        let r#move = &self.castling_move(r#move);
+        let castle = self.castle;
+        let en_passant = self.en_passant;
+        let halfmove_clock = self.halfmove_clock;
+        let hash = self.hash;
+        let pawn_move = matches!(
+            self.squares[r#move.from_square() as usize],
+            Some(Piece {
+                kind_of_piece: KindOfPiece::Pawn,
+                ..
+            })
+        );
+
+        let castling = self.castling_side(r#move);
+        let captured = self.apply(r#move);
+
+        // Bicie albo ruch pionem zeruje licznik
+        // zasady pięćdziesięciu ruchów.
+        if pawn_move || captured.is_some() {
+            self.halfmove_clock = 0;
         } else {
-            self.fields[(r#move.to_row() - 1) as usize][(r#move.to_file_number() - 1) as usize] =
-                field_content;
+            self.halfmove_clock += 1;
         }
 
-        if let Some(piece) = field_content {
-            // Krótka roszada.
-            // Przesuwamy wieżę.
-            if piece.kind_of_piece == KindOfPiece::King
-                && r#move.from_file_number() == 5
-                && r#move.to_file_number() == 7
-            {
-                self.fields[(r#move.from_row() - 1) as usize][7] = None;
-                self.fields[(r#move.from_row() - 1) as usize][5] = Some(Piece {
-                    kind_of_piece: KindOfPiece::Rook,
-                    color: piece.color,
-                });
-            }
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
 
-            // Długa roszada.
-            // Przesuwamy wieżę.
-            if piece.kind_of_piece == KindOfPiece::King
-                && r#move.from_file_number() == 5
-                && r#move.to_file_number() == 3
-            {
-                self.fields[(r#move.from_row() - 1) as usize][0] = None;
-                self.fields[(r#move.from_row() - 1) as usize][3] = Some(Piece {
-                    kind_of_piece: KindOfPiece::Rook,
-                    color: piece.color,
-                });
+        self.hash_history.push(hash);
+        self.next_turn();
+
+        UndoInfo {
+            captured,
+            castling,
+            castle,
+            en_passant,
+            halfmove_clock,
+            hash,
+        }
+    }
+
+    /// Legal moves of the player on move.
+    pub fn legal_moves(&self) -> MoveList {
+        player_moves(&self.turn, self)
+    }
+
+    /// Is the king of the player on move attacked.
+    pub fn in_check(&self) -> bool {
+        is_check(self, &self.turn)
+    }
+
+    /// Is the player on move checkmated.
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.legal_moves().is_empty()
+    }
+
+    /// Is the player on move stalemated.
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.legal_moves().is_empty()
+    }
+
+    /// Make move `move` like `make_move`,
+    /// but only if it is legal in the position.
+    pub fn apply_checked(&mut self, r#move: &Move) -> Result<UndoInfo, MoveError> {
+        if !self.legal_moves().contains(r#move) {
+            return Err(MoveError::Illegal);
+        }
+
+        Ok(self.make_move(r#move))
+    }
+
+    /// Make move written in UCI notation as `text`,
+    /// if it is legal in the position.
+    pub fn try_play(&mut self, text: &str, chess960: bool) -> Result<UndoInfo, MoveError> {
+        let r#move = move_from_uci(self, text, chess960)?;
+
+        self.apply_checked(&r#move)
+    }
+
+    /// Take back move `move` made with `make_move`.
+    pub fn unmake_move(&mut self, r#move: &Move, undo: UndoInfo) {
+        let from = r#move.from_square();
+        let to = r#move.to_square();
+
+        self.next_turn();
+        self.hash_history.pop();
+        self.halfmove_clock = undo.halfmove_clock;
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+
+        if let Some(side) = undo.castling {
+            // Wieża wraca na swoje pole startowe, niezależnie
+            // od tego, jak roszada była zapisana.
+            let (king_square, rook_square) = castle_destinations(&self.turn, &side);
+            let king = self.remove_piece(king_square);
+            let rook = self.remove_piece(rook_square);
+            if let Some(rook) = rook {
+                self.put_piece(
+                    self.castle_rook_square(castle_index(&self.turn, &side)),
+                    rook,
+                );
             }
+            if let Some(king) = king {
+                self.put_piece(from, king);
+            }
+        } else {
+            // Po `make_move` na polu docelowym zawsze stoi figura.
+            let piece = self
+                .remove_piece(to)
+                .expect("unmake_move called with a move that was not made");
+
+            if r#move.promotion().is_some() {
+                self.put_piece(
+                    from,
+                    Piece {
+                        kind_of_piece: KindOfPiece::Pawn,
+                        color: piece.color,
+                    },
+                );
+            } else {
+                self.put_piece(from, piece);
+            }
+        }
 
-            // Jeżeli ruszył się król:
-            // Unieważniamy roszady tego
-            // koloru.
-            if piece.kind_of_piece == KindOfPiece::King {
-                match piece.color {
-                    Color::White => {
-                        self.castle[0] = false;
-                        self.castle[1] = false;
-                    }
-                    Color::Black => {
-                        self.castle[2] = false;
-                        self.castle[3] = false;
+        if let Some((square, captured)) = undo.captured {
+            self.put_piece(square, captured);
+        }
+
+        self.castle = undo.castle;
+        self.en_passant = undo.en_passant;
+        self.hash = undo.hash;
+
+        debug_assert_eq!(self.hash, self.compute_hash());
+    }
+
+    /// Pass the turn to the other player without moving.
+    ///
+    /// Used by null-move pruning. The returned record
+    /// lets `unmake_null_move` take the pass back.
+    pub fn make_null_move(&mut self) -> UndoInfo {
+        let undo = UndoInfo {
+            captured: None,
+            castling: None,
+            castle: self.castle,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        };
+
+        // Powtórzenie nie może sięgać przez pas.
+        self.hash_history.push(self.hash);
+        self.halfmove_clock = 0;
+
+        // Po pasie nie da się bić w przelocie.
+        self.hash ^= self.rights_hash();
+        self.en_passant = [false; 8];
+        self.hash ^= self.rights_hash();
+        self.next_turn();
+
+        debug_assert_eq!(self.hash, self.compute_hash());
+
+        undo
+    }
+
+    /// Take back a pass made with `make_null_move`.
+    pub fn unmake_null_move(&mut self, undo: UndoInfo) {
+        self.turn = self.turn.enemy();
+        self.hash_history.pop();
+        self.halfmove_clock = undo.halfmove_clock;
+        self.en_passant = undo.en_passant;
+        self.hash = undo.hash;
+    }
+
+    /// Plies since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Number of the current move, starting at 1.
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// How many times the current position occurred before.
+    ///
+    /// Only positions since the last capture or pawn
+    /// move are looked at, since no earlier one can repeat.
+    pub fn repetitions(&self) -> usize {
+        self.hash_history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .filter(|hash| **hash == self.hash)
+            .count()
+    }
+
+    /// Is there too little material left for either player to mate.
+    ///
+    /// True for bare kings, a single minor piece and for
+    /// bishops only, all of them on squares of one color.
+    pub fn is_insufficient_material(&self) -> bool {
+        const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+
+        let heavy = self.pieces[KindOfPiece::Pawn.index()]
+            | self.pieces[KindOfPiece::Rook.index()]
+            | self.pieces[KindOfPiece::Queen.index()];
+        if heavy != 0 {
+            return false;
+        }
+
+        let knights = self.pieces[KindOfPiece::Knight.index()];
+        let bishops = self.pieces[KindOfPiece::Bishop.index()];
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /// Position in Forsyth-Edwards Notation.
+    ///
+    /// The en passant square is given only when the
+    /// capture is possible, like in the hash.
+    pub fn to_fen(&self) -> FENString {
+        let mut rows: [String; 8] = Default::default();
+
+        for (i, row) in rows.iter_mut().enumerate() {
+            let mut empty = 0;
+            for square in (8 * (7 - i))..(8 * (8 - i)) {
+                match self.squares[square] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+
+                        let letter = piece.kind_of_piece.to_string();
+                        match piece.color {
+                            Color::White => row.push_str(&letter.to_uppercase()),
+                            Color::Black => row.push_str(&letter),
+                        }
                     }
+                    None => empty += 1,
                 }
             }
 
-            // Jeżeli wieża ruszyła się
-            // ze swojego miejsca, unieważniamy
-            // tą roszadę.
-            if piece.kind_of_piece == KindOfPiece::Rook {
-                match (r#move.from_row(), r#move.from_file_number()) {
-                    (1, 1) => {
-                        self.castle[1] = false;
-                    }
-                    (1, 8) => {
-                        self.castle[0] = false;
-                    }
-                    (8, 1) => {
-                        self.castle[3] = false;
-                    }
-                    (8, 8) => {
-                        self.castle[2] = false;
-                    }
-                    _ => {}
-                }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
             }
+        }
 
-            // Biały bije przelotem.
-            if piece.color == Color::White
-                && self.en_passant[(r#move.to_file_number() - 1) as usize]
-                && r#move.to_row() == 6
-            {
-                let above_content = &mut self.fields[(r#move.to_row() - 2) as usize]
-                    [(r#move.to_file_number() - 1) as usize];
-                if let Some(piece) = above_content {
-                    if *piece
-                        == (Piece {
-                            kind_of_piece: KindOfPiece::Pawn,
-                            color: Color::Black,
-                        })
-                    {
-                        *above_content = None;
-                    }
+        // Literę kolumny piszemy tylko wtedy, gdy `K` albo `Q`
+        // wskazałoby inną, dalszą wieżę (X-FEN).
+        let mut castle: Vec<CastleRight> = Vec::new();
+        for (color, back_rank) in [(Color::White, RANK_1), (Color::Black, RANK_8)] {
+            let king = self.pieces(&color, &KindOfPiece::King) & back_rank;
+            let king_file = (king.trailing_zeros() % 8) as u8;
+            let rooks = self.pieces(&color, &KindOfPiece::Rook) & back_rank;
+            let rook_files = (0..8u8).filter(|file| rooks & (FILE_A << file) != 0);
+
+            let outermost_short = rook_files.clone().filter(|file| *file > king_file).max();
+            let outermost_long = rook_files.filter(|file| *file < king_file).min();
+
+            for side in [Castle::Short, Castle::Long] {
+                if !self.can_castle(&color, &side) {
+                    continue;
                 }
-            }
 
-            // Czarny bije przelotem.
-            if piece.color == Color::Black
-                && self.en_passant[(r#move.to_file_number() - 1) as usize]
-                && r#move.to_row() == 3
-            {
-                let under_content = &mut self.fields[(r#move.to_row()) as usize]
-                    [(r#move.to_file_number() - 1) as usize];
-                if let Some(piece) = under_content {
-                    if *piece
-                        == (Piece {
-                            kind_of_piece: KindOfPiece::Pawn,
-                            color: Color::White,
-                        })
-                    {
-                        *under_content = None;
+                let rook_file = self.castle_rook_file(&color, &side);
+                castle.push(match side {
+                    Castle::Short if outermost_short == Some(rook_file) => {
+                        CastleRight::Short(color)
                     }
-                }
+                    Castle::Long if outermost_long == Some(rook_file) => CastleRight::Long(color),
+                    _ => CastleRight::File(color, rook_file),
+                });
             }
+        }
 
-            // Unieważniamy możliwe bicia przelotem z poprzedniej rundy.
-            self.en_passant = [false; 8];
-
-            // Biały może być zbity przelotem.
-            if piece.color == Color::White && r#move.to_row() == 4 {
-                if (r#move.to_file_number() == 1
-                    || self.fields[(r#move.to_row() - 1) as usize]
-                        [(r#move.to_file_number() - 2) as usize]
-                        == Some(Piece {
-                            kind_of_piece: KindOfPiece::Pawn,
-                            color: Color::Black,
-                        }))
-                    && (r#move.to_file_number() == 8
-                        || self.fields[(r#move.to_row() - 1) as usize]
-                            [(r#move.to_file_number()) as usize]
-                            == Some(Piece {
-                                kind_of_piece: KindOfPiece::Pawn,
-                                color: Color::Black,
-                            }))
-                {
-                    self.en_passant[(r#move.to_file_number() - 1) as usize] = true;
-                }
+        FENString {
+            rows,
+            turn: self.turn,
+            castle,
+            en_passant: self.en_passant_square().map(Field::from_square),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+
+    /// Is the game drawn by threefold repetition,
+    /// the fifty-move rule or insufficient material.
+    pub fn is_draw(&self) -> bool {
+        self.repetitions() >= 2 || self.halfmove_clock >= 100 || self.is_insufficient_material()
+    }
+
+    /// Apply move and return the captured piece together
+    /// with the square it was captured on.
+    fn apply(&mut self, r#move: &Move) -> Option<(u8, Piece)> {
+        let from = r#move.from_square();
+        let to = r#move.to_square();
+
+        let side = self.castling_side(r#move);
+        let piece = self.remove_piece(from)?;
+        self.hash ^= self.rights_hash();
+
+        // Roszada zapisana jest jako bicie własnej wieży.
+        // Król i wieża stają na tych samych polach co
+        // w zwykłych szachach.
+        let mut captured = None;
+        if let Some(side) = side {
+            let (king_square, rook_square) = castle_destinations(&piece.color, &side);
+            if let Some(rook) = self.remove_piece(to) {
+                self.put_piece(rook_square, rook);
+            }
+            self.put_piece(king_square, piece);
+        } else {
+            captured = self.remove_piece(to).map(|captured| (to, captured));
+
+            match r#move.promotion() {
+                Some(kind_of_piece) => self.put_piece(
+                    to,
+                    Piece {
+                        kind_of_piece,
+                        color: piece.color,
+                    },
+                ),
+                None => self.put_piece(to, piece),
             }
+        }
 
-            // Czarny może być zbity przelotem.
-            if piece.color == Color::Black && r#move.to_row() == 5 {
-                if (r#move.to_file_number() == 1
-                    || self.fields[(r#move.to_row() - 1) as usize]
-                        [(r#move.to_file_number() - 2) as usize]
-                        == Some(Piece {
-                            kind_of_piece: KindOfPiece::Pawn,
-                            color: Color::White,
-                        }))
-                    && (r#move.to_file_number() == 8
-                        || self.fields[(r#move.to_row() - 1) as usize]
-                            [(r#move.to_file_number()) as usize]
-                            == Some(Piece {
-                                kind_of_piece: KindOfPiece::Pawn,
-                                color: Color::White,
-                            }))
-                {
-                    self.en_passant[(r#move.to_file_number() - 1) as usize] = true;
+        // Jeżeli ruszył się król:
+        // Unieważniamy roszady tego
+        // koloru.
+        if piece.kind_of_piece == KindOfPiece::King {
+            match piece.color {
+                Color::White => {
+                    self.castle[0] = false;
+                    self.castle[1] = false;
+                }
+                Color::Black => {
+                    self.castle[2] = false;
+                    self.castle[3] = false;
                 }
             }
         }
+
+        // Jeżeli wieża ruszyła się ze swojego
+        // miejsca albo została na nim zbita,
+        // unieważniamy tą roszadę.
+        for index in 0..4 {
+            let square = self.castle_rook_square(index);
+            if from == square || to == square {
+                self.castle[index] = false;
+            }
+        }
+
+        // Bicie przelotem: pion idzie na ukos
+        // na puste pole.
+        if piece.kind_of_piece == KindOfPiece::Pawn
+            && from % 8 != to % 8
+            && captured.is_none()
+            && self.en_passant[(to % 8) as usize]
+        {
+            let square = match piece.color {
+                Color::White => to - 8,
+                Color::Black => to + 8,
+            };
+            captured = self.remove_piece(square).map(|captured| (square, captured));
+        }
+
+        // Unieważniamy możliwe bicia przelotem z poprzedniej rundy.
+        self.en_passant = [false; 8];
+
+        // Pion po podwójnym ruchu może zostać zbity
+        // przelotem, jeżeli stoi obok niego pion przeciwnika.
+        if piece.kind_of_piece == KindOfPiece::Pawn && from.abs_diff(to) == 16 {
+            let passed_square = (from + to) / 2;
+            if pawn_attacks(&piece.color, passed_square)
+                & self.pieces(&piece.color.enemy(), &KindOfPiece::Pawn)
+                != 0
+            {
+                self.en_passant[(to % 8) as usize] = true;
+            }
+        }
+
+        self.hash ^= self.rights_hash();
+        debug_assert_eq!(self.hash, self.compute_hash());
+
+        captured
     }
 
     /// Content of field `field`.
     pub fn field_content(&self, field: &Field) -> &Option<Piece> {
-        &self.fields[(field.row - 1) as usize][(field.file - 1) as usize]
+        &self.squares[field.square() as usize]
+    }
+
+    /// Content of square `square`.
+    pub fn square_content(&self, square: u8) -> &Option<Piece> {
+        &self.squares[square as usize]
+    }
+
+    /// Squares taken by any piece.
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// Squares taken by pieces of color `color`.
+    pub fn occupied_by(&self, color: &Color) -> Bitboard {
+        self.colors[color.index()]
+    }
+
+    /// Squares taken by pieces of color `color` and kind `kind_of_piece`.
+    pub fn pieces(&self, color: &Color, kind_of_piece: &KindOfPiece) -> Bitboard {
+        self.colors[color.index()] & self.pieces[kind_of_piece.index()]
+    }
+
+    /// Square of the king of color `color`.
+    pub fn king_square(&self, color: &Color) -> Option<u8> {
+        squares(self.pieces(color, &KindOfPiece::King)).next()
+    }
+
+    /// Pieces of both colors attacking square `square`
+    /// when squares `occupied` are taken.
+    pub fn attackers_to(&self, square: u8, occupied: Bitboard) -> Bitboard {
+        let knights = self.pieces[KindOfPiece::Knight.index()];
+        let kings = self.pieces[KindOfPiece::King.index()];
+        let queens = self.pieces[KindOfPiece::Queen.index()];
+        let bishops = self.pieces[KindOfPiece::Bishop.index()] | queens;
+        let rooks = self.pieces[KindOfPiece::Rook.index()] | queens;
+
+        // Patrzymy z pola na zewnątrz: figura atakuje pole
+        // wtedy, gdy to samo pole atakuje ją "w drugą stronę".
+        (pawn_attacks(&Color::Black, square) & self.pieces(&Color::White, &KindOfPiece::Pawn))
+            | (pawn_attacks(&Color::White, square) & self.pieces(&Color::Black, &KindOfPiece::Pawn))
+            | (knight_attacks(square) & knights)
+            | (king_attacks(square) & kings)
+            | (bishop_attacks(square, occupied) & bishops)
+            | (rook_attacks(square, occupied) & rooks)
+    }
+
+    /// Is square `square` attacked by a piece of color `by_color`.
+    pub fn is_square_attacked(&self, square: u8, by_color: &Color) -> bool {
+        self.attackers_to(square, self.occupied()) & self.occupied_by(by_color) != 0
+    }
+
+    /// Pieces giving check to the player on move.
+    pub fn checkers(&self) -> Bitboard {
+        match self.king_square(&self.turn) {
+            Some(king) => {
+                self.attackers_to(king, self.occupied()) & self.occupied_by(&self.turn.enemy())
+            }
+            None => 0,
+        }
     }
 
     /// Which turn.
@@ -428,19 +921,16 @@ impl Board {
     /// Next turn.
     pub fn next_turn(&mut self) {
         self.turn = self.turn.enemy();
+        self.hash ^= RANDOM_TURN[0];
     }
 
     fn eval_players(&self, early_stage: bool) -> (i32, i32) {
         let mut pos_sum: i32 = 0;
         let mut neg_sum: i32 = 0;
 
-        for i in 1..=8 {
-            for j in 1..=8 {
-                let field = Field::build_unchecked(i, j);
-                let value = match self.field_content(&field) {
-                    Some(piece) => piece_value(&field, &piece, early_stage),
-                    None => 0,
-                };
+        for square in squares(self.occupied()) {
+            if let Some(ref piece) = self.squares[square as usize] {
+                let value = piece_value(&Field::from_square(square), piece, early_stage);
 
                 if value > 0 {
                     pos_sum += value;
@@ -467,15 +957,13 @@ impl Board {
     }
 }
 
-// TODO: Test apply_unchecked()
-
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut string: String = String::new();
 
         for row in 0..8 {
             for file in 0..8 {
-                let field_content = self.fields[row][file];
+                let field_content = self.squares[8 * row + file];
                 if let Some(piece) = field_content {
                     string += match piece.color {
                         Color::White => "w",
@@ -502,133 +990,132 @@ impl fmt::Display for Board {
 }
 
 impl std::convert::TryFrom<FENString> for Board {
-    type Error = ();
+    type Error = FENError;
 
     /// Create `Board` object from FENString.
     fn try_from(f: FENString) -> Result<Self, Self::Error> {
-        let mut fields: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
-        let mut castle: [bool; 4] = [false; 4];
-        let mut en_passant: [bool; 8] = [false; 8];
-        let turn: Color;
+        let mut board = Board::empty();
 
-        let f = f;
         for i in 0..f.rows.len() {
             let row = &f.rows[7 - i].as_bytes();
 
             let mut pos = 0usize;
             for p in row.iter() {
-                match p {
-                    num @ b'1'..=b'8' => {
-                        let num = *num - b'1' + 1;
-                        let end = num as usize + pos;
-                        for j in pos..end {
-                            fields[i][j] = None;
-                        }
-                        pos += num as usize;
-                    }
-                    b'p' => {
-                        fields[i][pos] = Some(BLACK_PAWN);
-                        pos += 1;
-                    }
-                    b'P' => {
-                        fields[i][pos] = Some(WHITE_PAWN);
-                        pos += 1;
-                    }
-                    b'r' => {
-                        fields[i][pos] = Some(BLACK_ROOK);
-                        pos += 1;
-                    }
-                    b'R' => {
-                        fields[i][pos] = Some(WHITE_ROOK);
-                        pos += 1;
-                    }
-                    b'n' => {
-                        fields[i][pos] = Some(BLACK_KNIGHT);
-                        pos += 1;
-                    }
-                    b'N' => {
-                        fields[i][pos] = Some(WHITE_KNIGHT);
-                        pos += 1;
-                    }
-                    b'b' => {
-                        fields[i][pos] = Some(BLACK_BISHOP);
-                        pos += 1;
-                    }
-                    b'B' => {
-                        fields[i][pos] = Some(WHITE_BISHOP);
-                        pos += 1;
-                    }
-                    b'q' => {
-                        fields[i][pos] = Some(BLACK_QUEEN);
-                        pos += 1;
-                    }
-                    b'Q' => {
-                        fields[i][pos] = Some(WHITE_QUEEN);
-                        pos += 1;
-                    }
-                    b'k' => {
-                        fields[i][pos] = Some(BLACK_KING);
-                        pos += 1;
-                    }
-                    b'K' => {
-                        fields[i][pos] = Some(WHITE_KING);
-                        pos += 1;
-                    }
-                    _ => return Err(()),
+                if let num @ b'1'..=b'8' = p {
+                    pos += (*num - b'0') as usize;
+                    continue;
+                }
+
+                let piece = match p {
+                    b'p' => BLACK_PAWN,
+                    b'P' => WHITE_PAWN,
+                    b'r' => BLACK_ROOK,
+                    b'R' => WHITE_ROOK,
+                    b'n' => BLACK_KNIGHT,
+                    b'N' => WHITE_KNIGHT,
+                    b'b' => BLACK_BISHOP,
+                    b'B' => WHITE_BISHOP,
+                    b'q' => BLACK_QUEEN,
+                    b'Q' => WHITE_QUEEN,
+                    b'k' => BLACK_KING,
+                    b'K' => WHITE_KING,
+                    _ => return Err(FENError::InvalidPiece(i as u8 + 1, *p as char)),
+                };
+
+                if pos >= 8 {
+                    return Err(FENError::InvalidRankLength(i as u8 + 1));
                 }
+
+                board.put_piece((8 * i + pos) as u8, piece);
+                pos += 1;
             }
 
             if pos != 8 {
-                return Err(());
+                return Err(FENError::InvalidRankLength(i as u8 + 1));
             }
         }
 
-        turn = match f.turn {
-            b'b' => Color::Black,
-            b'w' => Color::White,
-            _ => return Err(()),
-        };
+        board.turn = f.turn;
 
-        for p in f.castle {
-            match p {
-                b'A' => {
-                    castle[0] = true;
-                }
-                b'H' => {
-                    castle[1] = true;
-                }
-                b'a' => {
-                    castle[2] = true;
-                }
-                b'h' => {
-                    castle[3] = true;
-                }
-                b'-' => {}
-                _ => return Err(()),
-            };
+        let kings = |color| board.pieces(&color, &KindOfPiece::King).count_ones();
+        if (kings(Color::White), kings(Color::Black)) != (1, 1) {
+            return Err(FENError::WrongNumberOfKings(
+                kings(Color::White),
+                kings(Color::Black),
+            ));
         }
 
-        if f.en_passant != "-" {
-            let mut string: &str = f.en_passant.as_str();
-            let mut pos: &str;
-            while !string.is_empty() {
-                (pos, string) = string.split_at(cmp::min(2, string.len()));
+        let pawns = board.pieces[KindOfPiece::Pawn.index()];
+        if pawns & RANK_1 != 0 {
+            return Err(FENError::PawnOnBackRank(1));
+        }
+        if pawns & RANK_8 != 0 {
+            return Err(FENError::PawnOnBackRank(8));
+        }
 
-                let field = Field::try_from(pos)?;
-                if field.row != 3 && field.row != 6 {
-                    return Err(());
-                }
+        // Gracz, który właśnie się ruszył, nie mógł zostawić króla pod szachem.
+        if is_check(&board, &board.turn.enemy()) {
+            return Err(FENError::OpponentInCheck);
+        }
 
-                en_passant[(field.file - 1) as usize] = true;
+        for right in f.castle.iter() {
+            let (color, back_rank) = match right.color() {
+                Color::White => (Color::White, RANK_1),
+                Color::Black => (Color::Black, RANK_8),
+            };
+            let invalid = || FENError::InvalidCastling(right.to_string());
+
+            let king = board.pieces(&color, &KindOfPiece::King) & back_rank;
+            if king.count_ones() != 1 {
+                return Err(invalid());
+            }
+            let king_file = (king.trailing_zeros() % 8) as u8;
+            let rooks = board.pieces(&color, &KindOfPiece::Rook) & back_rank;
+            let rook_files = (0..8u8).filter(|file| rooks & (FILE_A << file) != 0);
+
+            // W X-FEN litera `K` albo `Q` oznacza
+            // najdalszą wieżę po tej stronie króla.
+            let rook_file = match right {
+                CastleRight::Short(_) => rook_files.filter(|file| *file > king_file).max(),
+                CastleRight::Long(_) => rook_files.filter(|file| *file < king_file).min(),
+                CastleRight::File(_, file) if rooks & (FILE_A << file) != 0 => Some(*file),
+                CastleRight::File(..) => None,
+            };
+            let rook_file = match rook_file {
+                Some(rook_file) if rook_file != king_file => rook_file,
+                _ => return Err(invalid()),
+            };
+
+            let side = if rook_file > king_file {
+                Castle::Short
+            } else {
+                Castle::Long
+            };
+            let index = castle_index(&color, &side);
+            if board.castle[index] {
+                return Err(invalid());
+            }
+
+            board.castle[index] = true;
+            board.castle_rook_files[index] = rook_file;
+        }
+        board.halfmove_clock = f.halfmove_clock;
+        board.fullmove_number = f.fullmove_number;
+
+        if let Some(field) = f.en_passant {
+            // Tak jak w Polyglocie, bicie przelotem zapamiętujemy
+            // tylko wtedy, gdy gracz na ruchu ma pion, który może bić.
+            if pawn_attacks(&board.turn.enemy(), field.square())
+                & board.pieces(&board.turn, &KindOfPiece::Pawn)
+                != 0
+            {
+                board.en_passant[(field.file - 1) as usize] = true;
             }
         }
 
-        Ok(Board {
-            fields,
-            castle,
-            en_passant,
-            turn,
-        })
+        board.hash = board.compute_hash();
+
+        Ok(board)
     }
 }
 
@@ -641,10 +1128,7 @@ pub struct Field {
 impl Field {
     pub fn build_unchecked(row: u8, file: u8) -> Self {
         match (file, row) {
-            (1..=8, 1..=8) => Field {
-                file: file as u8,
-                row: row as u8,
-            },
+            (1..=8, 1..=8) => Field { file, row },
             _ => panic!(),
         }
     }
@@ -659,6 +1143,16 @@ impl Field {
         }
     }
 
+    /// Field of bitboard square `square`.
+    pub fn from_square(square: u8) -> Self {
+        Field::build_unchecked(square / 8 + 1, square % 8 + 1)
+    }
+
+    /// Bitboard square of this field.
+    pub fn square(&self) -> u8 {
+        8 * (self.row - 1) + (self.file - 1)
+    }
+
     pub fn get_file(&self) -> u8 {
         self.file
     }
@@ -668,27 +1162,30 @@ impl Field {
     }
 }
 
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file - 1) as char, self.row)
+    }
+}
+
 impl std::convert::TryFrom<&str> for Field {
-    type Error = ();
+    type Error = FieldError;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let pos = s.as_bytes();
+        let pos: Vec<char> = s.chars().collect();
         if pos.len() != 2 {
-            return Err(());
+            return Err(FieldError::WrongLength(pos.len()));
         }
 
-        let file: u8 = if pos[0] >= b'a' && pos[0] <= b'h' {
-            pos[0] - b'a' + 1
-        } else if pos[0] >= b'A' && pos[0] <= b'H' {
-            pos[0] - b'A' + 1
-        } else {
-            return Err(());
+        let file: u8 = match pos[0] {
+            'a'..='h' => pos[0] as u8 - b'a' + 1,
+            'A'..='H' => pos[0] as u8 - b'A' + 1,
+            file => return Err(FieldError::InvalidFile(file)),
         };
 
-        let row: u8 = if pos[1] >= b'1' && pos[1] <= b'8' {
-            pos[1] - b'0'
-        } else {
-            return Err(());
+        let row: u8 = match pos[1] {
+            '1'..='8' => pos[1] as u8 - b'0',
+            row => return Err(FieldError::InvalidRank(row)),
         };
 
         Ok(Field { file, row })
@@ -697,34 +1194,104 @@ impl std::convert::TryFrom<&str> for Field {
 
 #[test]
 fn test_field() {
-    assert_eq!(Field::try_from("a"), Err(()));
-    assert_eq!(Field::try_from("a23"), Err(()));
+    assert_eq!(Field::try_from("a"), Err(FieldError::WrongLength(1)));
+    assert_eq!(Field::try_from("a23"), Err(FieldError::WrongLength(3)));
     assert_eq!(Field::try_from("a2"), Ok(Field { file: 1, row: 2 }));
     assert_eq!(Field::try_from("A2"), Ok(Field { file: 1, row: 2 }));
-    assert_eq!(Field::try_from("a0"), Err(()));
-    assert_eq!(Field::try_from("a9"), Err(()));
-    assert_eq!(Field::try_from("@2"), Err(()));
-    assert_eq!(Field::try_from("{2"), Err(()));
+    assert_eq!(Field::try_from("a0"), Err(FieldError::InvalidRank('0')));
+    assert_eq!(Field::try_from("a9"), Err(FieldError::InvalidRank('9')));
+    assert_eq!(Field::try_from("@2"), Err(FieldError::InvalidFile('@')));
+    assert_eq!(Field::try_from("{2"), Err(FieldError::InvalidFile('{')));
+    assert_eq!(Field::try_from("é2"), Err(FieldError::InvalidFile('é')));
+
+    assert_eq!(Field::try_from("a1").unwrap().square(), 0);
+    assert_eq!(Field::try_from("h8").unwrap().square(), 63);
+    assert_eq!(Field::from_square(12), Field::try_from("e2").unwrap());
 }
 
 pub fn field_code(piece: &Piece, field: &Field) -> u16 {
-    64u16 * (piece.code() as u16) + 8u16 * (field.row as u16) + (field.file as u16)
+    64u16 * (piece.code() as u16) + field.square() as u16
 }
 
 pub fn field_hash(piece: &Piece, field: &Field) -> u64 {
     RANDOM_PIECE[field_code(piece, field) as usize]
 }
 
-#[derive(Debug)]
+/// Castling right as written in FEN.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum CastleRight {
+    /// `K` or `k`: with the outermost rook on the king side.
+    Short(Color),
+    /// `Q` or `q`: with the outermost rook on the queen side.
+    Long(Color),
+    /// Shredder-FEN letter: with the rook on the given file, counted from 0.
+    File(Color, u8),
+}
+
+impl CastleRight {
+    fn color(&self) -> Color {
+        match self {
+            CastleRight::Short(color) | CastleRight::Long(color) | CastleRight::File(color, _) => {
+                *color
+            }
+        }
+    }
+}
+
+impl fmt::Display for CastleRight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = match self {
+            CastleRight::Short(_) => 'k',
+            CastleRight::Long(_) => 'q',
+            CastleRight::File(_, file) => (b'a' + file) as char,
+        };
+
+        match self.color() {
+            Color::White => write!(f, "{}", letter.to_ascii_uppercase()),
+            Color::Black => write!(f, "{}", letter),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct FENString {
     rows: [String; 8],
-    turn: u8,
-    castle: [u8; 4],
-    en_passant: String,
+    turn: Color,
+    castle: Vec<CastleRight>,
+    en_passant: Option<Field>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+}
+
+impl fmt::Display for FENString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let turn = match self.turn {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let castle: String = self.castle.iter().map(ToString::to_string).collect();
+
+        let en_passant = match self.en_passant {
+            Some(field) => field.to_string(),
+            None => "-".to_owned(),
+        };
+
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.rows.join("/"),
+            turn,
+            if castle.is_empty() { "-" } else { &castle },
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
 }
 
 impl std::convert::TryFrom<&str> for FENString {
-    type Error = ();
+    type Error = FENError;
 
     fn try_from(string: &str) -> Result<Self, Self::Error> {
         let tokens = string.split_whitespace().collect::<Vec<&str>>();
@@ -734,28 +1301,76 @@ impl std::convert::TryFrom<&str> for FENString {
 }
 
 impl std::convert::TryFrom<Vec<&str>> for FENString {
-    type Error = ();
+    type Error = FENError;
 
     fn try_from(tokens: Vec<&str>) -> Result<Self, Self::Error> {
         if tokens.len() < 4 || tokens.len() > 6 {
-            return Err(());
+            return Err(FENError::WrongNumberOfFields(tokens.len()));
         }
 
         let rows = tokens[0].split('/').collect::<Vec<&str>>();
         if rows.len() != 8 {
-            return Err(());
+            return Err(FENError::WrongNumberOfRanks(rows.len()));
         }
 
-        let turn: &[u8] = tokens[1].as_bytes();
-        if turn.len() != 1 {
-            return Err(());
-        }
+        let turn = match tokens[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            turn => return Err(FENError::InvalidTurn(turn.to_owned())),
+        };
 
-        let castle: &[u8] = tokens[2].as_bytes();
-        if castle.len() != 4 {
-            return Err(());
+        // Prawa do roszady: `-` albo litery `KQkq` (X-FEN)
+        // lub kolumny wież `A`-`H`, `a`-`h` (Shredder-FEN).
+        let mut castle: Vec<CastleRight> = Vec::new();
+        if tokens[2] != "-" {
+            for p in tokens[2].bytes() {
+                let right = match p {
+                    b'K' => CastleRight::Short(Color::White),
+                    b'Q' => CastleRight::Long(Color::White),
+                    b'k' => CastleRight::Short(Color::Black),
+                    b'q' => CastleRight::Long(Color::Black),
+                    b'A'..=b'H' => CastleRight::File(Color::White, p - b'A'),
+                    b'a'..=b'h' => CastleRight::File(Color::Black, p - b'a'),
+                    _ => return Err(FENError::InvalidCastling(tokens[2].to_owned())),
+                };
+
+                if castle.contains(&right) {
+                    return Err(FENError::InvalidCastling(tokens[2].to_owned()));
+                }
+                castle.push(right);
+            }
         }
 
+        let en_passant = match tokens[3] {
+            "-" => None,
+            en_passant => {
+                let expected_row = match turn {
+                    Color::White => 6,
+                    Color::Black => 3,
+                };
+
+                match Field::try_from(en_passant) {
+                    Ok(field) if field.row == expected_row => Some(field),
+                    _ => return Err(FENError::InvalidEnPassant(en_passant.to_owned())),
+                }
+            }
+        };
+
+        // Ostatnie dwa pola można pominąć.
+        let halfmove_clock = match tokens.get(4) {
+            Some(token) => token
+                .parse::<u16>()
+                .map_err(|_| FENError::InvalidHalfmoveClock(token.to_string()))?,
+            None => 0,
+        };
+        let fullmove_number = match tokens.get(5) {
+            Some(token) => match token.parse::<u16>() {
+                Ok(number) if number > 0 => number,
+                _ => return Err(FENError::InvalidFullmoveNumber(token.to_string())),
+            },
+            None => 1,
+        };
+
         Ok(FENString {
             rows: rows
                 .into_iter()
@@ -763,9 +1378,11 @@ impl std::convert::TryFrom<Vec<&str>> for FENString {
                 .collect::<Vec<String>>()
                 .try_into()
                 .unwrap(),
-            turn: turn[0],
-            castle: castle.try_into().unwrap(),
-            en_passant: tokens[3].to_owned(),
+            turn,
+            castle,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
         })
     }
 }
@@ -774,13 +1391,522 @@ impl std::convert::TryFrom<Vec<&str>> for FENString {
 fn test_fenstring() {
     let f = match FENString::try_from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah -") {
         Ok(f) => f,
-        Err(()) => panic!(),
+        Err(_) => panic!(),
     };
 
     let board = match Board::try_from(f) {
         Ok(board) => board,
-        Err(()) => panic!(),
+        Err(_) => panic!(),
     };
 
     assert_eq!(board, Board::new());
+
+    let fen = |string: &str| Board::try_from(FENString::try_from(string)?);
+    assert_eq!(
+        fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        Ok(Board::new())
+    );
+
+    // Częściowe prawa do roszady.
+    let board = fen("r3k2r/8/8/8/8/8/8/R3K2R b Kq - 3 12").unwrap();
+    assert!(board.can_castle(&Color::White, &Castle::Short));
+    assert!(!board.can_castle(&Color::White, &Castle::Long));
+    assert!(!board.can_castle(&Color::Black, &Castle::Short));
+    assert!(board.can_castle(&Color::Black, &Castle::Long));
+    assert_eq!(board.halfmove_clock(), 3);
+    assert_eq!(board.fullmove_number(), 12);
+
+    assert!(fen("8/8/8/8/8/8/8/4K2k w - -").is_ok());
+    assert_eq!(
+        fen("8/8/8/8/8/8/8 w - -"),
+        Err(FENError::WrongNumberOfRanks(7))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/8 w"),
+        Err(FENError::WrongNumberOfFields(2))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2x w - -"),
+        Err(FENError::InvalidPiece(1, 'x'))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k1 w - -"),
+        Err(FENError::InvalidRankLength(1))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k x - -"),
+        Err(FENError::InvalidTurn("x".to_owned()))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k w KK -"),
+        Err(FENError::InvalidCastling("KK".to_owned()))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k w - e3"),
+        Err(FENError::InvalidEnPassant("e3".to_owned()))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k w - - x"),
+        Err(FENError::InvalidHalfmoveClock("x".to_owned()))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K2k w - - 0 0"),
+        Err(FENError::InvalidFullmoveNumber("0".to_owned()))
+    );
+}
+
+#[test]
+fn test_invalid_position() {
+    let fen = |string: &str| Board::try_from(FENString::try_from(string)?);
+
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/8 w - -"),
+        Err(FENError::WrongNumberOfKings(0, 0))
+    );
+    assert_eq!(
+        fen("8/8/8/8/8/8/8/4K3 w - -"),
+        Err(FENError::WrongNumberOfKings(1, 0))
+    );
+    assert_eq!(
+        fen("k7/8/8/8/8/8/8/K3K3 w - -"),
+        Err(FENError::WrongNumberOfKings(2, 1))
+    );
+    assert_eq!(
+        fen("k7/8/8/8/8/8/8/K2P4 w - -"),
+        Err(FENError::PawnOnBackRank(1))
+    );
+    assert_eq!(
+        fen("k2p4/8/8/8/8/8/8/K7 w - -"),
+        Err(FENError::PawnOnBackRank(8))
+    );
+    // Czarny król jest szachowany, a ruch mają białe.
+    assert_eq!(
+        fen("k7/8/8/8/8/8/8/R3K3 w - -"),
+        Err(FENError::OpponentInCheck)
+    );
+    assert!(fen("k7/8/8/8/8/8/8/R3K3 b - -").is_ok());
+}
+
+#[test]
+fn test_to_fen() {
+    assert_eq!(
+        Board::new().to_fen().to_string(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 3 12",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ] {
+        let board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+        assert_eq!(board.to_fen().to_string(), fen);
+    }
+}
+
+#[test]
+fn test_chess960_fen() {
+    let fen = |string: &str| Board::try_from(FENString::try_from(string)?);
+
+    // Shredder-FEN: kolumny wież zamiast `KQkq`.
+    let board = fen("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1").unwrap();
+    assert_eq!(board.castle_rook_file(&Color::White, &Castle::Short), 6);
+    assert_eq!(board.castle_rook_file(&Color::White, &Castle::Long), 4);
+    assert_eq!(board.castle_rook_file(&Color::Black, &Castle::Short), 6);
+    assert_eq!(board.castle_rook_file(&Color::Black, &Castle::Long), 4);
+    assert_eq!(
+        board.to_fen().to_string(),
+        "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1"
+    );
+    assert_eq!(
+        fen("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1"),
+        Ok(board)
+    );
+
+    // X-FEN: wewnętrzna wieża musi być podana kolumną.
+    let board = fen("1r2k1rr/8/8/8/8/8/8/1R2K1RR w Gq - 0 1").unwrap();
+    assert_eq!(board.castle_rook_file(&Color::White, &Castle::Short), 6);
+    assert!(!board.can_castle(&Color::White, &Castle::Long));
+    assert_eq!(board.castle_rook_file(&Color::Black, &Castle::Long), 1);
+    assert_eq!(
+        board.to_fen().to_string(),
+        "1r2k1rr/8/8/8/8/8/8/1R2K1RR w Gq - 0 1"
+    );
+    let board = fen("1r2k1rr/8/8/8/8/8/8/1R2K1RR w K - 0 1").unwrap();
+    assert_eq!(board.castle_rook_file(&Color::White, &Castle::Short), 7);
+
+    // Dwa prawa do tej samej roszady.
+    assert_eq!(
+        fen("1r2k1rr/8/8/8/8/8/8/1R2K1RR w GH - 0 1"),
+        Err(FENError::InvalidCastling("H".to_owned()))
+    );
+
+    // Ruch wieży odbiera tylko jej roszadę.
+    let mut board = fen("1r2k1rr/8/8/8/8/8/8/1R2K1RR w GB - 0 1").unwrap();
+    board.make_move(&Move::try_from("h1h2").unwrap());
+    assert!(board.can_castle(&Color::White, &Castle::Short));
+    board.make_move(&Move::try_from("h8h7").unwrap());
+    board.make_move(&Move::try_from("g1g2").unwrap());
+    assert!(!board.can_castle(&Color::White, &Castle::Short));
+    assert!(board.can_castle(&Color::White, &Castle::Long));
+
+    assert_eq!(
+        fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+        Err(FENError::InvalidCastling("K".to_owned()))
+    );
+    assert_eq!(
+        fen("4k3/8/8/8/8/8/8/R3K3 w C - 0 1"),
+        Err(FENError::InvalidCastling("C".to_owned()))
+    );
+}
+
+#[test]
+fn test_fen_round_trip() {
+    // Prosty generator liniowy, żeby partie były powtarzalne.
+    let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut random = |bound: usize| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize % bound
+    };
+
+    // Losowe partie: każda pozycja po drodze musi przejść
+    // przez FEN bez zmian.
+    for _ in 0..20 {
+        let mut board = Board::new();
+        for _ in 0..80 {
+            let fen = board.to_fen();
+            let parsed =
+                Board::try_from(FENString::try_from(fen.to_string().as_str()).unwrap()).unwrap();
+            assert_eq!(
+                FENString::try_from(fen.to_string().as_str()),
+                Ok(fen.clone())
+            );
+            assert_eq!(parsed.to_fen(), fen);
+            assert_eq!(parsed.hash(), board.hash());
+            assert_eq!(parsed.squares, board.squares);
+            assert_eq!(parsed.castle, board.castle);
+            assert_eq!(parsed.castle_rook_files, board.castle_rook_files);
+            assert_eq!(parsed.en_passant, board.en_passant);
+
+            let moves = board.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            board.make_move(&moves[random(moves.len())]);
+        }
+    }
+}
+
+#[test]
+fn test_apply_unchecked() {
+    let mut board = Board::new();
+    for r#move in ["e2e4", "g8f6", "e4e5", "d7d5"] {
+        board.apply_unchecked(&Move::try_from(r#move).unwrap());
+        board.next_turn();
+    }
+
+    // Czarny pion stoi obok białego, więc można bić przelotem.
+    assert!(board.can_en_passant(4));
+    assert_eq!(board.en_passant_square(), Some(43));
+
+    board.apply_unchecked(&Move::try_from("e5d6").unwrap());
+    board.next_turn();
+    assert_eq!(*board.square_content(35), None);
+    assert_eq!(*board.square_content(43), Some(WHITE_PAWN));
+    assert_eq!(
+        board.pieces(&Color::Black, &KindOfPiece::Pawn).count_ones(),
+        7
+    );
+    assert_eq!(board.occupied().count_ones(), 31);
+    assert!(!board.can_en_passant(4));
+}
+
+#[test]
+fn test_make_unmake_move() {
+    let mut board = Board::try_from(
+        FENString::try_from("r3k2r/pPpp1ppp/8/4p3/3P4/8/P1PP1PPP/R3K2R w AHah -").unwrap(),
+    )
+    .unwrap();
+
+    let mut history: Vec<(Board, Move, UndoInfo)> = Vec::new();
+    for r#move in [
+        "d4e5", "d7d5", "e5d6", "e8a8", "b7a8q", "h8h2", "e1h1", "h2h1",
+    ] {
+        let before = board.clone();
+        let r#move = Move::try_from(r#move).unwrap();
+        let undo = board.make_move(&r#move);
+        history.push((before, r#move, undo));
+    }
+
+    assert_eq!(*board.square_content(56), Some(WHITE_QUEEN));
+    assert_eq!(*board.square_content(59), Some(BLACK_ROOK));
+    assert_eq!(*board.square_content(35), None);
+    assert_eq!(*board.square_content(6), Some(WHITE_KING));
+    assert_eq!(board.which_turn(), Color::White);
+
+    while let Some((before, r#move, undo)) = history.pop() {
+        board.unmake_move(&r#move, undo);
+        assert_eq!(board, before);
+    }
+}
+
+#[test]
+fn test_chess960_castling() {
+    // Król na b1, wieże na a1 i f1.
+    let mut board =
+        Board::try_from(FENString::try_from("1k6/8/8/8/8/8/8/RK3R2 w FA - 0 1").unwrap()).unwrap();
+    let before = board.clone();
+
+    // Krótka roszada: wieża zostaje na f1, król idzie na g1.
+    let r#move = Move::try_from("b1f1").unwrap();
+    assert!(matches!(board.castling_side(&r#move), Some(Castle::Short)));
+    let undo = board.make_move(&r#move);
+    assert_eq!(*board.square_content(6), Some(WHITE_KING));
+    assert_eq!(*board.square_content(5), Some(WHITE_ROOK));
+    assert_eq!(*board.square_content(1), None);
+    assert_eq!(*board.square_content(2), None);
+    assert!(!board.can_castle(&Color::White, &Castle::Long));
+    assert_eq!(board.halfmove_clock(), 1);
+    board.unmake_move(&r#move, undo);
+    assert_eq!(board, before);
+
+    // Długa roszada: król na c1, wieża a1 na d1.
+    let r#move = Move::try_from("b1a1").unwrap();
+    assert!(matches!(board.castling_side(&r#move), Some(Castle::Long)));
+    let undo = board.make_move(&r#move);
+    assert_eq!(*board.square_content(2), Some(WHITE_KING));
+    assert_eq!(*board.square_content(3), Some(WHITE_ROOK));
+    assert_eq!(*board.square_content(0), None);
+    board.unmake_move(&r#move, undo);
+    assert_eq!(board, before);
+
+    // Zwykły ruch króla nie jest roszadą.
+    assert!(board
+        .castling_side(&Move::try_from("b1b2").unwrap())
+        .is_none());
+}
+
+#[test]
+fn test_standard_castling_notation() {
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+    let mut board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+    let before = board.clone();
+
+    // Król idący o dwa pola to też roszada.
+    let r#move = Move::try_from("e1g1").unwrap();
+    assert_eq!(
+        board.castling_move(&r#move),
+        Move::try_from("e1h1").unwrap()
+    );
+    board.apply_unchecked(&r#move);
+    assert_eq!(*board.square_content(6), Some(WHITE_KING));
+    assert_eq!(*board.square_content(5), Some(WHITE_ROOK));
+    assert_eq!(*board.square_content(7), None);
+
+    let mut board = before.clone();
+    let r#move = Move::try_from("e1c1").unwrap();
+    let undo = board.make_move(&r#move);
+    assert_eq!(*board.square_content(2), Some(WHITE_KING));
+    assert_eq!(*board.square_content(3), Some(WHITE_ROOK));
+    assert_eq!(*board.square_content(0), None);
+    board.unmake_move(&r#move, undo);
+    assert_eq!(board, before);
+
+    // Zwykły ruch króla zostaje bez zmian.
+    let r#move = Move::try_from("e1f1").unwrap();
+    assert_eq!(board.castling_move(&r#move), r#move);
+}
+
+#[test]
+fn test_apply_checked() {
+    let mut board = Board::new();
+    let before = board.clone();
+
+    assert_eq!(
+        board.apply_checked(&Move::try_from("e2e5").unwrap()).err(),
+        Some(MoveError::Illegal)
+    );
+    assert_eq!(
+        board.try_play("e1e2", false).err(),
+        Some(MoveError::Illegal)
+    );
+    assert_eq!(
+        board.try_play("e7e5", false).err(),
+        Some(MoveError::Illegal)
+    );
+    assert_eq!(
+        board.try_play("e2", false).err(),
+        Some(MoveError::WrongLength(2))
+    );
+    assert_eq!(board, before);
+
+    for r#move in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"] {
+        assert!(board.try_play(r#move, false).is_ok());
+    }
+    assert_eq!(*board.square_content(6), Some(WHITE_KING));
+    assert_eq!(*board.square_content(5), Some(WHITE_ROOK));
+
+    // Król nie może wejść pod szach.
+    let mut board =
+        Board::try_from(FENString::try_from("4k3/8/8/8/8/8/8/r3K3 w - -").unwrap()).unwrap();
+    assert_eq!(board.try_play("e1e2", false).err(), None);
+    assert_eq!(board.try_play("e8d8", false).err(), None);
+    assert_eq!(
+        board.try_play("e2d1", false).err(),
+        Some(MoveError::Illegal)
+    );
+}
+
+#[test]
+fn test_legal_moves() {
+    let fen = |fen| Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+
+    let board = Board::new();
+    assert_eq!(board.legal_moves().len(), 20);
+    assert!(board
+        .legal_moves()
+        .contains(&Move::try_from("g1f3").unwrap()));
+    assert!(!board.in_check());
+    assert!(!board.is_checkmate());
+    assert!(!board.is_stalemate());
+
+    // Mat szewski.
+    let board = fen("r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4");
+    assert!(board.in_check());
+    assert!(board.is_checkmate());
+    assert!(!board.is_stalemate());
+    assert!(board.legal_moves().is_empty());
+
+    let board = fen("k7/8/1Q6/8/8/8/8/7K b - -");
+    assert!(!board.in_check());
+    assert!(!board.is_checkmate());
+    assert!(board.is_stalemate());
+
+    // Szach: król ucieka na e2 albo f2, skoczek
+    // zasłania na c1 albo bije wieżę.
+    let board = fen("4k3/8/8/8/8/1N6/3P4/r3K3 w - -");
+    assert!(board.in_check());
+    assert!(!board.is_checkmate());
+    assert_eq!(board.legal_moves().len(), 4);
+}
+
+#[test]
+fn test_draw_rules() {
+    let mut board = Board::new();
+    for r#move in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+        assert!(!board.is_draw());
+        board.make_move(&Move::try_from(r#move).unwrap());
+    }
+
+    assert_eq!(board.repetitions(), 1);
+    assert_eq!(board.halfmove_clock(), 7);
+    assert_eq!(board.fullmove_number(), 4);
+    board.make_move(&Move::try_from("f6g8").unwrap());
+    assert_eq!(board.repetitions(), 2);
+    assert!(board.is_draw());
+
+    // Ruch pionem zeruje licznik i kończy powtórzenia.
+    board.make_move(&Move::try_from("e2e4").unwrap());
+    assert_eq!(board.halfmove_clock(), 0);
+    assert_eq!(board.repetitions(), 0);
+
+    let board =
+        Board::try_from(FENString::try_from("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap()).unwrap();
+    assert_eq!(board.fullmove_number(), 80);
+    assert!(board.is_draw());
+
+    for (fen, insufficient) in [
+        ("4k3/8/8/8/8/8/8/4K3 w - -", true),
+        ("4k3/8/8/8/8/8/8/2N1K3 w - -", true),
+        ("2b1k3/8/8/8/8/8/8/2B1K3 w - -", false),
+        ("3bk3/8/8/8/8/8/8/2B1K3 w - -", true),
+        ("4k3/8/8/8/8/8/8/1NN1K3 w - -", false),
+        ("4k3/8/8/8/8/8/P7/4K3 w - -", false),
+    ] {
+        let board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+        assert_eq!(board.is_insufficient_material(), insufficient, "{}", fen);
+    }
+}
+
+#[test]
+fn test_null_move() {
+    let mut board = Board::new();
+    board.make_move(&Move::try_from("e2e4").unwrap());
+    board.make_move(&Move::try_from("d7d5").unwrap());
+    board.make_move(&Move::try_from("e4e5").unwrap());
+    board.make_move(&Move::try_from("f7f5").unwrap());
+    assert!(board.en_passant_square().is_some());
+
+    let before = board.clone();
+    let undo = board.make_null_move();
+    assert_eq!(board.which_turn(), Color::Black);
+    assert_eq!(board.en_passant_square(), None);
+    assert_ne!(board.hash(), before.hash());
+
+    board.unmake_null_move(undo);
+    assert_eq!(board, before);
+}
+
+#[test]
+fn test_hash() {
+    // Klucze z opisu formatu Polyglot.
+    let games: [(&[&str], u64); 9] = [
+        (&[], 0x463b96181691fc9c),
+        (&["e2e4"], 0x823c9b50fd114196),
+        (&["e2e4", "d7d5"], 0x0756b94461c50fb0),
+        (&["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4),
+        (&["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78),
+        (
+            &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"],
+            0x652a607ca3f242c1,
+        ),
+        (
+            &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"],
+            0x00fdd303c946bdd9,
+        ),
+        (
+            &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"],
+            0x3c8123ea7b067637,
+        ),
+        (
+            &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"],
+            0x5c3f9b829b279560,
+        ),
+    ];
+
+    for (moves, key) in games {
+        let mut board = Board::new();
+        for r#move in moves {
+            board.make_move(&Move::try_from(*r#move).unwrap());
+        }
+
+        assert_eq!(board.hash(), key);
+        assert_eq!(board.hash(), board.compute_hash());
+    }
+}
+
+#[test]
+fn test_attacks() {
+    let board =
+        Board::try_from(FENString::try_from("4k3/8/8/1b6/8/3N4/8/4K2R w - -").unwrap()).unwrap();
+
+    assert_eq!(board.checkers(), 0);
+    assert!(board.is_square_attacked(Field::try_from("d3").unwrap().square(), &Color::Black));
+    // Skoczek na d3 zasłania e2 przed gońcem.
+    assert!(!board.is_square_attacked(Field::try_from("e2").unwrap().square(), &Color::Black));
+    assert!(board.is_square_attacked(Field::try_from("h8").unwrap().square(), &Color::White));
+    assert!(board.is_square_attacked(Field::try_from("b4").unwrap().square(), &Color::White));
+    assert!(!board.is_square_attacked(Field::try_from("e4").unwrap().square(), &Color::White));
+
+    let mut board = board;
+    board.apply_unchecked(&Move::try_from("d3c5").unwrap());
+    board.next_turn();
+    board.apply_unchecked(&Move::try_from("b5a4").unwrap());
+    board.next_turn();
+    board.apply_unchecked(&Move::try_from("h1h8").unwrap());
+    board.next_turn();
+    assert_eq!(board.checkers(), square_bb(63));
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
-use std::error::Error;
 use std::fmt;
 use std::fs::File;
+use std::io;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
 
@@ -9,28 +9,39 @@ use std::io::prelude::*;
 
 use super::board::Field;
 use super::board::KindOfPiece;
+use super::error::BookError;
+use super::error::MoveError;
 
 struct BookFileEntry {
     key: u64,
     r#move: u16,
     weight: u16,
-    learn: u32,
+    // We don't read `learn`.
 }
 
-fn read_entry(file: &mut File, entry: &mut BookFileEntry) -> Result<(), Box<dyn Error>> {
+/// Read the next entry, or `None` at the end of the file.
+fn read_entry(file: &mut File, index: usize) -> Result<Option<BookFileEntry>, BookError> {
     let mut buf = [0; 16];
-    file.read_exact(&mut buf)?;
+    match file.read_exact(&mut buf) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error.into()),
+    }
 
-    entry.key = u64::from_be_bytes(buf[0..8].try_into()?);
-    entry.r#move = u16::from_be_bytes(buf[8..10].try_into()?);
-    entry.weight = u16::from_be_bytes(buf[10..12].try_into()?);
-    entry.learn = u32::from_be_bytes(buf[12..16].try_into()?);
+    let entry = BookFileEntry {
+        key: u64::from_be_bytes(buf[0..8].try_into().unwrap()),
+        r#move: u16::from_be_bytes(buf[8..10].try_into().unwrap()),
+        weight: u16::from_be_bytes(buf[10..12].try_into().unwrap()),
+    };
 
-    if entry.r#move & 0x8000 != 0 {
-        return Err(anyhow::anyhow!("Malformed data.").into());
+    if Move::try_from(entry.r#move).is_err() {
+        return Err(BookError::InvalidMove {
+            index,
+            code: entry.r#move,
+        });
     }
 
-    Ok(())
+    Ok(Some(entry))
 }
 
 #[derive(Debug)]
@@ -40,6 +51,7 @@ pub struct BookEntry {
     // We don't use information in `learn`.
 }
 
+#[derive(Default)]
 pub struct Book(HashMap<u64, Vec<BookEntry>>);
 
 impl Deref for Book {
@@ -57,31 +69,31 @@ impl DerefMut for Book {
 }
 
 impl Book {
-    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
-        let mut file = match File::open(&path) {
-            Err(_) => return None,
-            Ok(file) => file,
-        };
+    /// Load Polyglot book from file `path`.
+    ///
+    /// Reading stops at the first entry with an invalid move.
+    /// The entries read so far are kept and returned together
+    /// with the error, so that the caller can report it.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<(Self, Option<BookError>), BookError> {
+        let mut file = File::open(&path)?;
 
         let mut book = Book(HashMap::new());
-        let mut entry = BookFileEntry {
-            key: 0,
-            r#move: 0,
-            weight: 0,
-            learn: 0,
-        };
-        while read_entry(&mut file, &mut entry).is_ok() {
-            if book.get(&entry.key).is_none() {
-                book.insert(entry.key, Vec::new());
-            }
-
-            book.get_mut(&entry.key).unwrap().push(BookEntry {
+        let mut index = 0;
+        loop {
+            let entry = match read_entry(&mut file, index) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(error @ BookError::InvalidMove { .. }) => return Ok((book, Some(error))),
+                Err(error) => return Err(error),
+            };
+            book.entry(entry.key).or_default().push(BookEntry {
                 r#move: entry.r#move,
                 weight: entry.weight,
             });
+            index += 1;
         }
 
-        Some(book)
+        Ok((book, None))
     }
 }
 
@@ -89,19 +101,39 @@ impl Book {
 pub struct Move(u16);
 
 impl Move {
+    /// Polyglot encoding of the move.
+    pub fn code(&self) -> u16 {
+        self.0
+    }
+
+    /// Move with Polyglot encoding `code`, if it is a valid one.
+    /// Tables storing moves as codes use 0 for no move.
+    pub fn from_code(code: u16) -> Option<Self> {
+        // Kod 0 to ruch a1a1, więc oznacza brak ruchu.
+        match code {
+            0 => None,
+            code => Move::try_from(code).ok(),
+        }
+    }
+
     pub fn from_file_number(&self) -> u8 {
         (((self.0 & 0x1C0) >> 6) + 1) as u8
     }
 
     pub fn from_file(&self) -> char {
         const ALPHABET: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-        char::from(ALPHABET[(self.from_file_number() - 1) as usize])
+        ALPHABET[(self.from_file_number() - 1) as usize]
     }
 
     pub fn from_row(&self) -> u8 {
         (((self.0 & 0xE00) >> 9) + 1) as u8
     }
 
+    /// Bitboard square the move starts from.
+    pub fn from_square(&self) -> u8 {
+        ((self.0 >> 6) & 0x3F) as u8
+    }
+
     pub fn from_field(&self) -> Field {
         Field::build_unchecked(self.from_row(), self.from_file_number())
     }
@@ -112,13 +144,18 @@ impl Move {
 
     pub fn to_file(&self) -> char {
         const ALPHABET: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-        char::from(ALPHABET[(self.to_file_number() - 1) as usize])
+        ALPHABET[(self.to_file_number() - 1) as usize]
     }
 
     pub fn to_row(&self) -> u8 {
         (((self.0 & 0x38) >> 3) + 1) as u8
     }
 
+    /// Bitboard square the move ends on.
+    pub fn to_square(&self) -> u8 {
+        (self.0 & 0x3F) as u8
+    }
+
     pub fn to_field(&self) -> Field {
         Field::build_unchecked(self.to_row(), self.to_file_number())
     }
@@ -134,7 +171,9 @@ impl Move {
         }
     }
 
-    pub fn build(f1: Field, f2: Field, piece: Option<KindOfPiece>) -> Result<Self, ()> {
+    /// Move from `f1` to `f2`, promoting to `piece` if given.
+    /// Returns `None` if `piece` cannot be promoted to.
+    pub fn build(f1: Field, f2: Field, piece: Option<KindOfPiece>) -> Option<Self> {
         let mut code: u16 = 0;
 
         code |= (f2.get_file() - 1) as u16;
@@ -148,23 +187,30 @@ impl Move {
             Some(KindOfPiece::Bishop) => 2,
             Some(KindOfPiece::Rook) => 3,
             Some(KindOfPiece::Queen) => 4,
-            _ => return Err(()),
+            _ => return None,
         } << 12;
 
-        Ok(Move(code))
+        Some(Move(code))
+    }
+}
+
+/// Move with code 0, which Polyglot uses for no move.
+impl Default for Move {
+    fn default() -> Self {
+        Move(0)
     }
 }
 
 impl std::convert::TryFrom<u16> for Move {
-    type Error = ();
+    type Error = MoveError;
 
     fn try_from(code: u16) -> Result<Self, Self::Error> {
         if code & 0x8000 != 0 {
-            return Err(());
+            return Err(MoveError::InvalidCode(code));
         }
 
         if (code & 0x7000) >> 12 > 4 {
-            return Err(());
+            return Err(MoveError::InvalidCode(code));
         }
 
         Ok(Move(code))
@@ -172,38 +218,27 @@ impl std::convert::TryFrom<u16> for Move {
 }
 
 impl std::convert::TryFrom<&str> for Move {
-    type Error = ();
+    type Error = MoveError;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        if s.len() != 4 && s.len() != 5 {
-            return Err(());
+        let chars: Vec<char> = s.chars().collect();
+        if chars.len() != 4 && chars.len() != 5 {
+            return Err(MoveError::WrongLength(chars.len()));
         }
 
-        let (s1, mut s2) = s.split_at(2);
-        let mut m: &str = &String::new();
-        if s2.len() == 3 {
-            (s2, m) = s2.split_at(2);
-        }
-
-        let f1 = match Field::try_from(s1) {
-            Ok(field) => field,
-            Err(()) => return Err(()),
-        };
-        let f2 = match Field::try_from(s2) {
-            Ok(field) => field,
-            Err(()) => return Err(()),
-        };
+        let f1 = Field::try_from(&*chars[0..2].iter().collect::<String>())?;
+        let f2 = Field::try_from(&*chars[2..4].iter().collect::<String>())?;
 
-        let piece: Option<KindOfPiece> = match m {
-            "" => None,
-            "q" => Some(KindOfPiece::Queen),
-            "b" => Some(KindOfPiece::Bishop),
-            "n" => Some(KindOfPiece::Knight),
-            "r" => Some(KindOfPiece::Rook),
-            _ => return Err(()),
+        let piece: Option<KindOfPiece> = match chars.get(4) {
+            None => None,
+            Some('q') => Some(KindOfPiece::Queen),
+            Some('b') => Some(KindOfPiece::Bishop),
+            Some('n') => Some(KindOfPiece::Knight),
+            Some('r') => Some(KindOfPiece::Rook),
+            Some(piece) => return Err(MoveError::InvalidPromotion(*piece)),
         };
 
-        Move::build(f1, f2, piece)
+        Ok(Move::build(f1, f2, piece).unwrap())
     }
 }
 
@@ -234,9 +269,24 @@ impl fmt::Display for Move {
 
 #[test]
 fn test_move() {
-    assert_eq!(Move::try_from(0x8000u16), Err(()));
-    assert_eq!(Move::try_from(0xF000u16), Err(()));
-    assert_eq!(Move::try_from(0x7000u16), Err(()));
+    use super::error::FieldError;
+
+    assert_eq!(Move::from_code(0), None);
+    assert_eq!(Move::from_code(0x8000), None);
+    assert_eq!(Move::from_code(0x031C), Move::try_from(0x031Cu16).ok());
+
+    assert_eq!(
+        Move::try_from(0x8000u16),
+        Err(MoveError::InvalidCode(0x8000))
+    );
+    assert_eq!(
+        Move::try_from(0xF000u16),
+        Err(MoveError::InvalidCode(0xF000))
+    );
+    assert_eq!(
+        Move::try_from(0x7000u16),
+        Err(MoveError::InvalidCode(0x7000))
+    );
 
     let m = match Move::try_from(0x031Cu16) {
         Ok(m) => m,
@@ -252,4 +302,77 @@ fn test_move() {
 
     assert_eq!(Move::try_from(0x031Cu16), Move::try_from("e2e4"));
     assert_eq!(Move::try_from(0x4D3Cu16), Move::try_from("e7e8q"));
+
+    for (text, promotion) in [
+        ("e7e8q", KindOfPiece::Queen),
+        ("e7e8r", KindOfPiece::Rook),
+        ("e7e8b", KindOfPiece::Bishop),
+        ("e7e8n", KindOfPiece::Knight),
+    ] {
+        let m = Move::try_from(text).unwrap();
+        assert_eq!(m.promotion(), Some(promotion));
+        assert_eq!(m.to_string(), text);
+        assert_eq!(
+            Move::build(m.from_field(), m.to_field(), Some(promotion)),
+            Some(m)
+        );
+        assert_eq!(Move::try_from(m.code()), Ok(m));
+    }
+    assert_eq!(
+        Move::build(
+            Field::try_from("e7").unwrap(),
+            Field::try_from("e8").unwrap(),
+            Some(KindOfPiece::King)
+        ),
+        None
+    );
+
+    assert_eq!(Move::try_from("e2e"), Err(MoveError::WrongLength(3)));
+    assert_eq!(
+        Move::try_from("e2i4"),
+        Err(MoveError::InvalidField(FieldError::InvalidFile('i')))
+    );
+    assert_eq!(
+        Move::try_from("e9e4"),
+        Err(MoveError::InvalidField(FieldError::InvalidRank('9')))
+    );
+    assert_eq!(
+        Move::try_from("e7e8k"),
+        Err(MoveError::InvalidPromotion('k'))
+    );
+    assert_eq!(
+        Move::try_from("e7é8"),
+        Err(MoveError::InvalidField(FieldError::InvalidFile('é')))
+    );
+}
+
+#[test]
+fn test_load_truncated() {
+    let path = std::env::temp_dir().join(format!("vendace-book-{}.bin", std::process::id()));
+
+    // Drugi wpis ma niepoprawny kod ruchu.
+    let mut data = Vec::new();
+    for (key, code) in [(1u64, 0x031Cu16), (2, 0x8000), (3, 0x031C)] {
+        data.extend_from_slice(&key.to_be_bytes());
+        data.extend_from_slice(&code.to_be_bytes());
+        data.extend_from_slice(&[0, 1, 0, 0, 0, 0]);
+    }
+    std::fs::write(&path, data).unwrap();
+
+    let (book, warning) = Book::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        warning,
+        Some(BookError::InvalidMove {
+            index: 1,
+            code: 0x8000
+        })
+    ));
+    assert_eq!(book.len(), 1);
+    assert_eq!(book[&1][0].r#move, 0x031C);
+
+    assert!(matches!(
+        Book::load(&path),
+        Err(BookError::Io(error)) if error.kind() == io::ErrorKind::NotFound
+    ));
 }
@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Reason why a square name like `e4` was rejected.
+#[derive(Clone, PartialEq, Debug)]
+pub enum FieldError {
+    /// Name has this many characters instead of 2.
+    WrongLength(usize),
+    /// File is not a letter from `a` to `h`.
+    InvalidFile(char),
+    /// Rank is not a digit from `1` to `8`.
+    InvalidRank(char),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::WrongLength(length) => {
+                write!(f, "expected 2 characters, found {}", length)
+            }
+            FieldError::InvalidFile(file) => write!(f, "invalid file `{}`", file),
+            FieldError::InvalidRank(rank) => write!(f, "invalid rank `{}`", rank),
+        }
+    }
+}
+
+impl Error for FieldError {}
+
+/// Reason why a move in UCI notation
+/// or in Polyglot encoding was rejected.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MoveError {
+    /// Move has this many characters instead of 4 or 5.
+    WrongLength(usize),
+    /// Square the move starts from or ends on is invalid.
+    InvalidField(FieldError),
+    /// Piece to promote to is not one of `q`, `r`, `b`, `n`.
+    InvalidPromotion(char),
+    /// Polyglot code does not describe a move.
+    InvalidCode(u16),
+    /// Move is well formed, but not legal in the position.
+    Illegal,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::WrongLength(length) => {
+                write!(f, "expected 4 or 5 characters, found {}", length)
+            }
+            MoveError::InvalidField(error) => write!(f, "invalid square: {}", error),
+            MoveError::InvalidPromotion(piece) => {
+                write!(f, "invalid promotion piece `{}`", piece)
+            }
+            MoveError::InvalidCode(code) => write!(f, "invalid move code {:#06x}", code),
+            MoveError::Illegal => write!(f, "illegal move"),
+        }
+    }
+}
+
+impl Error for MoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MoveError::InvalidField(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<FieldError> for MoveError {
+    fn from(error: FieldError) -> Self {
+        MoveError::InvalidField(error)
+    }
+}
+
+/// Reason why a FEN string was rejected.
+#[derive(Clone, PartialEq, Debug)]
+pub enum FENError {
+    /// FEN has this many fields instead of 4 to 6.
+    WrongNumberOfFields(usize),
+    /// Piece placement has this many ranks instead of 8.
+    WrongNumberOfRanks(usize),
+    /// Unknown piece letter in the rank with this number.
+    InvalidPiece(u8, char),
+    /// Rank with this number does not describe exactly 8 squares.
+    InvalidRankLength(u8),
+    /// Side to move is neither `w` nor `b`.
+    InvalidTurn(String),
+    /// Castling field is not `-` nor a set of `KQkq` or rook file
+    /// letters, or a right does not match the kings and rooks.
+    InvalidCastling(String),
+    /// En passant field is not `-` nor a square on the 3rd or 6th rank.
+    InvalidEnPassant(String),
+    /// Halfmove clock is not a number.
+    InvalidHalfmoveClock(String),
+    /// Fullmove number is not a positive number.
+    InvalidFullmoveNumber(String),
+    /// Position has this many white and black
+    /// kings instead of exactly one each.
+    WrongNumberOfKings(u32, u32),
+    /// Rank with this number, the first or the last, holds a pawn.
+    PawnOnBackRank(u8),
+    /// King of the player who is not on move is in check.
+    OpponentInCheck,
+}
+
+impl fmt::Display for FENError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FENError::WrongNumberOfFields(count) => {
+                write!(f, "expected 4 to 6 fields, found {}", count)
+            }
+            FENError::WrongNumberOfRanks(count) => {
+                write!(f, "expected 8 ranks, found {}", count)
+            }
+            FENError::InvalidPiece(rank, piece) => {
+                write!(f, "invalid piece `{}` in rank {}", piece, rank)
+            }
+            FENError::InvalidRankLength(rank) => {
+                write!(f, "rank {} does not have 8 squares", rank)
+            }
+            FENError::InvalidTurn(turn) => write!(f, "invalid side to move `{}`", turn),
+            FENError::InvalidCastling(castle) => {
+                write!(f, "invalid castling rights `{}`", castle)
+            }
+            FENError::InvalidEnPassant(en_passant) => {
+                write!(f, "invalid en passant square `{}`", en_passant)
+            }
+            FENError::InvalidHalfmoveClock(clock) => {
+                write!(f, "invalid halfmove clock `{}`", clock)
+            }
+            FENError::InvalidFullmoveNumber(number) => {
+                write!(f, "invalid fullmove number `{}`", number)
+            }
+            FENError::WrongNumberOfKings(white, black) => write!(
+                f,
+                "expected one king per side, found {} white and {} black",
+                white, black
+            ),
+            FENError::PawnOnBackRank(rank) => write!(f, "pawn on rank {}", rank),
+            FENError::OpponentInCheck => write!(f, "player not on move is in check"),
+        }
+    }
+}
+
+impl Error for FENError {}
+
+/// Reason why a Polyglot opening book could not be loaded.
+#[derive(Debug)]
+pub enum BookError {
+    /// Book file could not be read.
+    Io(io::Error),
+    /// Entry number `index` holds an invalid move code.
+    InvalidMove { index: usize, code: u16 },
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookError::Io(error) => write!(f, "cannot read book: {}", error),
+            BookError::InvalidMove { index, code } => {
+                write!(f, "entry {} has invalid move code {:#06x}", index, code)
+            }
+        }
+    }
+}
+
+impl Error for BookError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BookError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BookError {
+    fn from(error: io::Error) -> Self {
+        BookError::Io(error)
+    }
+}
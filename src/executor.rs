@@ -10,18 +10,65 @@ use super::board::FENString;
 use super::book::Book;
 use super::book::BookEntry;
 use super::book::Move;
+use super::moves::move_to_uci;
+use super::perft::divide;
 
-use super::moves::get_move;
-use super::moves::STOP_ALL_THREADS;
+use super::search::get_move;
+use super::search::SearchOptions;
+use super::search::STOP_ALL_THREADS;
+use super::tt::TranspositionTable;
+use super::tt::DEFAULT_HASH_MB;
 
 lazy_static! {
-    static ref BOOK: Book = Book::load("/usr/share/gnuchess/smallbook.bin").unwrap();
+    static ref BOOK: Book = match Book::load("/usr/share/gnuchess/smallbook.bin") {
+        Ok((book, None)) => book,
+        Ok((book, Some(error))) => {
+            // Uszkodzona końcówka: używamy tego, co udało się wczytać.
+            println!("info string Error: {}, using the entries before it.", error);
+            book
+        }
+        Err(error) => {
+            // Bez książki po prostu liczymy każdy ruch.
+            println!("info string Error: {}.", error);
+            Book::default()
+        }
+    };
+}
+
+static JOB_COUNTER: AtomicU8 = AtomicU8::new(0);
+
+/// Split `setoption name <id> [value <x>]` into option name and value.
+fn parse_setoption(tokens: &[&str]) -> Option<(String, String)> {
+    if tokens.get(1) != Some(&"name") {
+        return None;
+    }
+
+    let rest = &tokens[2..];
+    let (name, value) = match rest.iter().position(|token| *token == "value") {
+        Some(pos) => (&rest[..pos], &rest[pos + 1..]),
+        None => (rest, &rest[rest.len()..]),
+    };
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name.join(" "), value.join(" ")))
 }
 
-static mut JOB_COUNTER: AtomicU8 = AtomicU8::new(0);
+/// Value of an option of type `check`.
+fn parse_check(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
 
 pub fn executor(rx: mpsc::Receiver<String>) {
     let mut board: Board = Board::new();
+    let mut tt = TranspositionTable::new(DEFAULT_HASH_MB);
+    let mut options = SearchOptions::default();
 
     for cmd in rx.iter() {
         let tokens = cmd.split_whitespace().collect::<Vec<&str>>();
@@ -34,30 +81,45 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                 let mut pos_set: bool = false;
                 let mut mov_set: bool = false;
 
+                // Pozycję budujemy na kopii, żeby po błędzie
+                // zostawić poprzednią.
+                let mut position = board.clone();
+                let mut rejected = false;
+
                 let mut i: usize = 1;
                 'parse_commands: while i < tokens.len() {
                     match tokens[i] {
                         "fen" => {
                             if pos_set || mov_set {
-                                println!("Error: Malformed `position` command string.");
+                                println!("info string Error: Malformed `position` command string.");
+                                rejected = true;
                                 break;
                             }
 
-                            let fs = match FENString::try_from(tokens[i + 1..=i + 4].to_vec()) {
+                            // Fenstring ciągnie się do `moves` albo do końca.
+                            let end = tokens[i + 1..]
+                                .iter()
+                                .position(|token| *token == "moves")
+                                .map_or(tokens.len(), |pos| i + 1 + pos);
+
+                            let fs = match FENString::try_from(tokens[i + 1..end].to_vec()) {
                                 Ok(fs) => fs,
-                                Err(()) => {
-                                    println!("Error: Malformed fenstring.");
+                                Err(error) => {
+                                    println!("info string Error: Malformed fenstring: {}.", error);
+                                    rejected = true;
                                     break;
                                 }
                             };
 
                             // Przesuwamy iterator za fenstring.
-                            i += 4;
+                            i = end - 1;
 
-                            board = match Board::try_from(fs) {
+                            // Poprawny zapis może opisywać niemożliwą pozycję.
+                            position = match Board::try_from(fs) {
                                 Ok(board) => board,
-                                Err(()) => {
-                                    println!("Error: Malformed fenstring.");
+                                Err(error) => {
+                                    println!("info string Error: Illegal position: {}.", error);
+                                    rejected = true;
                                     break;
                                 }
                             };
@@ -65,11 +127,12 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                         }
                         "startpos" => {
                             if pos_set || mov_set {
-                                println!("Error: Malformed `position` command string.");
+                                println!("info string Error: Malformed `position` command string.");
+                                rejected = true;
                                 break;
                             }
 
-                            board = Board::new();
+                            position = Board::new();
                             pos_set = true;
                         }
                         "moves" => {
@@ -77,16 +140,15 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                             while i < tokens.len() {
                                 let token = tokens[i];
 
-                                let r#move = match Move::try_from(token) {
-                                    Ok(r#move) => r#move,
-                                    Err(()) => {
-                                        println!("Invalid move in `position` command string.");
-                                        break 'parse_commands;
-                                    }
-                                };
+                                if let Err(error) = position.try_play(token, options.chess960) {
+                                    println!(
+                                        "info string Error: Invalid move `{}`: {}.",
+                                        token, error
+                                    );
+                                    rejected = true;
+                                    break 'parse_commands;
+                                }
 
-                                board.apply_unchecked(&r#move);
-                                board.next_turn();
                                 i += 1;
                             }
 
@@ -97,12 +159,35 @@ pub fn executor(rx: mpsc::Receiver<String>) {
 
                     i += 1;
                 }
+
+                if !rejected {
+                    board = position;
+                }
             }
-            "go" => {
-                unsafe {
-                    let _ = JOB_COUNTER.fetch_add(1, Ordering::SeqCst);
+            "go" if tokens.get(1) == Some(&"perft") => {
+                let depth = match tokens.get(2).map(|token| u8::from_str(token)) {
+                    Some(Ok(depth)) => depth,
+                    _ => {
+                        println!("info string Error: Malformed `go perft` command string.");
+                        continue;
+                    }
+                };
+
+                let mut position = board.clone();
+                let (moves, nodes) = divide(&mut position, depth);
+                for (r#move, count) in moves {
+                    println!(
+                        "{}: {}",
+                        move_to_uci(&board, &r#move, options.chess960),
+                        count
+                    );
                 }
 
+                println!("\nNodes searched: {}", nodes);
+            }
+            "go" => {
+                let _ = JOB_COUNTER.fetch_add(1, Ordering::SeqCst);
+
                 // Odczytaj wtime/btime
                 let mut my_time: u64 = 8000;
                 let mut i = 1;
@@ -110,34 +195,30 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                     let token = tokens[i];
 
                     match token {
-                        "wtime" => {
-                            if board.which_turn() == Color::White {
-                                if tokens.len() == i + 1 {
-                                    // FIXME: Malformed command error.
-                                    break;
-                                }
+                        "wtime" if board.which_turn() == Color::White => {
+                            if tokens.len() == i + 1 {
+                                // FIXME: Malformed command error.
+                                break;
+                            }
 
-                                if let Ok(time) = FromStr::from_str(tokens[i + 1]) {
-                                    my_time = std::cmp::min(my_time, time);
-                                } else {
-                                    // FIXME: Malformed command error.
-                                    break;
-                                }
+                            if let Ok(time) = FromStr::from_str(tokens[i + 1]) {
+                                my_time = std::cmp::min(my_time, time);
+                            } else {
+                                // FIXME: Malformed command error.
+                                break;
                             }
                         }
-                        "btime" => {
-                            if board.which_turn() == Color::Black {
-                                if tokens.len() == i + 1 {
-                                    // FIXME: Malformed command error.
-                                    break;
-                                }
+                        "btime" if board.which_turn() == Color::Black => {
+                            if tokens.len() == i + 1 {
+                                // FIXME: Malformed command error.
+                                break;
+                            }
 
-                                if let Ok(time) = FromStr::from_str(tokens[i + 1]) {
-                                    my_time = std::cmp::min(my_time, time);
-                                } else {
-                                    // FIXME: Malformed command error.
-                                    break;
-                                }
+                            if let Ok(time) = FromStr::from_str(tokens[i + 1]) {
+                                my_time = std::cmp::min(my_time, time);
+                            } else {
+                                // FIXME: Malformed command error.
+                                break;
                             }
                         }
                         _ => {}
@@ -147,20 +228,16 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                 }
 
                 std::thread::spawn(move || {
-                    let job_num = unsafe { JOB_COUNTER.load(Ordering::SeqCst) };
+                    let job_num = JOB_COUNTER.load(Ordering::SeqCst);
                     std::thread::sleep(Duration::from_millis(my_time));
 
-                    if job_num == unsafe { JOB_COUNTER.load(Ordering::SeqCst) } {
-                        unsafe {
-                            *STOP_ALL_THREADS.get_mut() = true;
-                        }
+                    if job_num == JOB_COUNTER.load(Ordering::SeqCst) {
+                        STOP_ALL_THREADS.store(true, Ordering::SeqCst);
                     }
                 });
 
                 // Odblokuj obliczenia.
-                unsafe {
-                    *STOP_ALL_THREADS.get_mut() = false;
-                }
+                STOP_ALL_THREADS.store(false, Ordering::SeqCst);
 
                 if let Some(moves) = BOOK.get(&board.hash()) {
                     let (mut best_move, mut best_weight) = (0u16, 0u16);
@@ -170,11 +247,57 @@ pub fn executor(rx: mpsc::Receiver<String>) {
                         }
                     }
 
-                    println!("bestmove {}", Move::try_from(best_move).unwrap());
+                    let r#move = Move::try_from(best_move).unwrap();
+                    println!(
+                        "bestmove {}",
+                        move_to_uci(&board, &r#move, options.chess960)
+                    );
                 } else {
-                    let r#move = get_move(&board, &board.which_turn());
+                    match get_move(&board, &tt, &options) {
+                        Some(r#move) => println!(
+                            "bestmove {}",
+                            move_to_uci(&board, &r#move, options.chess960)
+                        ),
+                        // Mat albo pat: UCI oznacza brak ruchu przez 0000.
+                        None => println!("bestmove 0000"),
+                    }
+                }
+            }
+            "ucinewgame" => {
+                tt.clear();
+            }
+            "setoption" => {
+                let (name, value) = match parse_setoption(&tokens) {
+                    Some(option) => option,
+                    None => {
+                        println!("info string Error: Malformed `setoption` command string.");
+                        continue;
+                    }
+                };
 
-                    println!("bestmove {}", r#move);
+                if name.eq_ignore_ascii_case("Hash") {
+                    match usize::from_str(&value) {
+                        Ok(megabytes) => tt = TranspositionTable::new(megabytes),
+                        Err(_) => println!("info string Error: Invalid value of option `Hash`."),
+                    }
+                } else if let Some(option) = [
+                    ("NullMove", &mut options.null_move),
+                    ("LateMoveReductions", &mut options.late_move_reductions),
+                    ("FutilityPruning", &mut options.futility_pruning),
+                    ("SeventhRankExtension", &mut options.seventh_rank_extension),
+                    ("UCI_Chess960", &mut options.chess960),
+                ]
+                .into_iter()
+                .find(|(option_name, _)| name.eq_ignore_ascii_case(option_name))
+                {
+                    match parse_check(&value) {
+                        Some(enabled) => *option.1 = enabled,
+                        None => {
+                            println!("info string Error: Invalid value of option `{}`.", option.0)
+                        }
+                    }
+                } else {
+                    println!("info string Error: Unknown option `{}`.", name);
                 }
             }
             _ => unreachable!(),
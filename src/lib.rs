@@ -1,9 +1,14 @@
 #[macro_use]
 pub extern crate lazy_static;
 
+pub mod bitboard;
 pub mod board;
 pub mod book;
+pub mod error;
 pub mod executor;
 pub mod moves;
+pub mod perft;
 pub mod polyglot_data;
+pub mod search;
 pub mod tables;
+pub mod tt;
@@ -1,9 +1,12 @@
 use std::fs::File;
 use std::io::{self, Write};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 
 use vendace::executor::executor;
-use vendace::moves::STOP_ALL_THREADS;
+use vendace::search::STOP_ALL_THREADS;
+use vendace::tt::DEFAULT_HASH_MB;
+use vendace::tt::MAX_HASH_MB;
 
 fn main() {
     println!("Vendace 1.0.0 by Antoni Przybylik");
@@ -43,12 +46,25 @@ fn main() {
                 println!("readyok");
             }
             "ucinewgame" => {
-                // Do nothing.
+                tx.send(input).unwrap();
             }
             "uci" => {
-                // TODO: Print engine options.
+                println!("id name Vendace");
+                println!("id author Antoni Przybylik");
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                );
+                println!("option name NullMove type check default true");
+                println!("option name LateMoveReductions type check default true");
+                println!("option name FutilityPruning type check default true");
+                println!("option name SeventhRankExtension type check default true");
+                println!("option name UCI_Chess960 type check default false");
                 println!("uciok");
             }
+            "setoption" => {
+                tx.send(input).unwrap();
+            }
             "position" => {
                 tx.send(input).unwrap();
             }
@@ -62,9 +78,9 @@ fn main() {
             "quit" => {
                 std::process::exit(0);
             }
-            "stop" => unsafe {
-                *STOP_ALL_THREADS.get_mut() = true;
-            },
+            "stop" => {
+                STOP_ALL_THREADS.store(true, Ordering::SeqCst);
+            }
             _ => {
                 println!(
                     "Unknown command: '{}'. Type help for more information.",
@@ -1,573 +1,1039 @@
-use std::sync::atomic::AtomicBool;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::AtomicU16;
 use std::sync::atomic::Ordering;
 
+use super::bitboard::between;
+use super::bitboard::bishop_attacks;
+use super::bitboard::king_attacks;
+use super::bitboard::knight_attacks;
+use super::bitboard::pawn_attacks;
+use super::bitboard::queen_attacks;
+use super::bitboard::rook_attacks;
+use super::bitboard::square_bb;
+use super::bitboard::squares;
+use super::bitboard::Bitboard;
+use super::bitboard::RANK_1;
+use super::bitboard::RANK_2;
+use super::bitboard::RANK_7;
+use super::bitboard::RANK_8;
+use super::board::castle_destinations;
 use super::board::Board;
 use super::board::Castle;
 use super::board::Color;
 use super::board::Field;
 use super::board::KindOfPiece;
-use super::board::Piece;
 use super::book::Move;
+use super::error::MoveError;
+use super::tables::kind_value;
+
+/// Capacity of `MoveList`. No position has
+/// more than 218 legal moves.
+pub const MAX_MOVES: usize = 256;
+
+/// List of moves kept on the stack, so generating
+/// moves does not allocate.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
 
-use rayon::prelude::*;
-
-fn is_check(board: &Board, color: &Color) -> bool {
-    let enemy_color = color.enemy();
-    let mut enemy_positions: Vec<Field> = Vec::new();
-
-    for row in 1..=8 {
-        for file in 1..=8 {
-            let field = Field::build_unchecked(row, file);
-            if let Some(piece) = board.field_content(&field) {
-                if piece.color == enemy_color {
-                    enemy_positions.push(field);
-                }
-            }
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList {
+            moves: [Move::default(); MAX_MOVES],
+            len: 0,
         }
     }
 
-    let mut enemy_view: Vec<Field> = Vec::new();
-    for field in enemy_positions.iter() {
-        enemy_view.append(&mut possible_moves_unchecked(field, board));
+    pub fn push(&mut self, r#move: Move) {
+        self.moves[self.len] = r#move;
+        self.len += 1;
     }
+}
 
-    for field in enemy_view {
-        if *board.field_content(&field)
-            == Some(Piece {
-                kind_of_piece: KindOfPiece::King,
-                color: *color,
-            })
-        {
-            return true;
-        }
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
     }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
 
-    false
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.len]
+    }
 }
 
-fn possible_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let field_content = board.field_content(field);
-    if *field_content == None {
-        return Vec::new();
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.moves[..self.len]
     }
+}
 
-    let piece = field_content.unwrap();
-    match piece.kind_of_piece {
-        KindOfPiece::King => king_moves_unchecked(field, board),
-        KindOfPiece::Queen => queen_moves_unchecked(field, board),
-        KindOfPiece::Bishop => bishop_moves_unchecked(field, board),
-        KindOfPiece::Knight => knight_moves_unchecked(field, board),
-        KindOfPiece::Rook => rook_moves_unchecked(field, board),
-        KindOfPiece::Pawn => pawn_moves_unchecked(field, board),
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Move, MAX_MOVES>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.into_iter().take(self.len)
     }
 }
 
-fn possible_moves(field: &Field, board: &Board) -> Vec<Move> {
-    let field_content = board.field_content(field);
-    if *field_content == None {
-        return Vec::new();
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
-    let piece = field_content.unwrap();
+}
 
-    let fields = possible_moves_unchecked(field, board);
-    let mut moves: Vec<Move> = Vec::new();
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
 
-    for target_field in fields.into_iter() {
-        if piece.kind_of_piece == KindOfPiece::Pawn
-            && (target_field.get_row() == 1 || target_field.get_row() == 8)
-        {
-            moves.push(Move::build(field.clone(), target_field, Some(KindOfPiece::Queen)).unwrap());
-            moves
-                .push(Move::build(field.clone(), target_field, Some(KindOfPiece::Knight)).unwrap());
-            // Nie ma co rozważać wieży i gońca bo nie dają nic więcej od hetmana.
-        } else {
-            moves.push(Move::build(field.clone(), target_field, None).unwrap());
-        }
+/// Is the king of color `color` attacked.
+pub fn is_check(board: &Board, color: &Color) -> bool {
+    match board.king_square(color) {
+        Some(king) => board.is_square_attacked(king, &color.enemy()),
+        None => false,
+    }
+}
+
+/// Kind of piece captured by move `move`, if it is a capture.
+pub fn captured_kind(board: &Board, r#move: &Move) -> Option<KindOfPiece> {
+    if board.castling_side(r#move).is_some() {
+        return None;
     }
 
-    let mut filtered_moves: Vec<Move> = Vec::new();
-    for r#move in moves.into_iter() {
-        let mut cloned_board = *board;
+    if let Some(piece) = board.square_content(r#move.to_square()) {
+        return Some(piece.kind_of_piece);
+    }
 
-        // Roszada: Sprawdzamy czy król nie przechodzi
-        // przez szachowane pole.
-        if piece.kind_of_piece == KindOfPiece::King
-            && (r#move.to_file_number() as i16 - r#move.from_file_number() as i16).abs() >= 2
+    // Bicie przelotem: pion idzie na ukos na puste pole.
+    match board.square_content(r#move.from_square()) {
+        Some(piece)
+            if piece.kind_of_piece == KindOfPiece::Pawn
+                && r#move.from_file_number() != r#move.to_file_number() =>
         {
-            // Roszada: Sprawdzamy czy król nie przechodzi
-            // przez szachowane pole.
-            let row = r#move.to_row();
-            let start_file = r#move.from_file_number();
-            let stop_file = r#move.to_file_number();
-
-            for file in if start_file < stop_file {
-                start_file..=stop_file
-            } else {
-                stop_file..=start_file
-            } {
-                cloned_board.fields[(row - 1) as usize][(file - 1) as usize] = Some(Piece {
-                    kind_of_piece: KindOfPiece::King,
-                    color: piece.color,
-                });
-            }
-        } else {
-            // Nie-Roszada: Sprawdzamy czy po ruchu nie
-            // ma szacha.
-            cloned_board.apply_unchecked(&r#move);
+            Some(KindOfPiece::Pawn)
         }
+        _ => None,
+    }
+}
 
-        if !is_check(&cloned_board, &piece.color) {
-            filtered_moves.push(r#move);
+/// Move `move` in position `board` written in UCI notation.
+///
+/// Without `chess960` castling is written as a king
+/// move by two squares, otherwise as the king
+/// capturing its own rook.
+pub fn move_to_uci(board: &Board, r#move: &Move, chess960: bool) -> String {
+    match board.castling_side(r#move) {
+        Some(side) if !chess960 => {
+            let color = board.which_turn();
+            let (king_to, _) = castle_destinations(&color, &side);
+
+            format!(
+                "{}{}",
+                Field::from_square(r#move.from_square()),
+                Field::from_square(king_to)
+            )
         }
+        _ => r#move.to_string(),
     }
-
-    filtered_moves
 }
 
-pub fn get_move(board: &Board, turn: &Color) -> Move {
-    let mut r#move: Option<Move> = None;
-
-    for depth in 2..100 {
-        r#move = match minimax_multithreaded(board, turn, depth) {
-            (Some(r#move), _) => Some(r#move),
-            _ => break,
-        };
+/// Move written in UCI notation as `text` in position `board`.
+/// Castling is read like `move_to_uci` writes it.
+pub fn move_from_uci(board: &Board, text: &str, chess960: bool) -> Result<Move, MoveError> {
+    let r#move = Move::try_from(text)?;
+    if chess960 {
+        return Ok(r#move);
     }
 
-    r#move.unwrap()
+    Ok(board.castling_move(&r#move))
 }
 
-pub static mut STOP_ALL_THREADS: AtomicBool = AtomicBool::new(true);
+fn possible_moves_unchecked(square: u8, board: &Board) -> Bitboard {
+    let piece = match board.square_content(square) {
+        Some(piece) => piece,
+        None => return 0,
+    };
 
-fn minimax_multithreaded(board: &Board, turn: &Color, depth: u8) -> (Option<Move>, i32) {
-    // Forced stop.
-    if unsafe { STOP_ALL_THREADS.load(Ordering::SeqCst) } == true {
-        return (None, 0);
+    let occupied = board.occupied();
+    let own = board.occupied_by(&piece.color);
+    match piece.kind_of_piece {
+        KindOfPiece::King => king_moves_unchecked(square, board),
+        KindOfPiece::Queen => queen_attacks(square, occupied) & !own,
+        KindOfPiece::Bishop => bishop_attacks(square, occupied) & !own,
+        KindOfPiece::Knight => knight_attacks(square) & !own,
+        KindOfPiece::Rook => rook_attacks(square, occupied) & !own,
+        KindOfPiece::Pawn => pawn_moves_unchecked(square, board),
     }
+}
 
-    if depth < 4 {
-        return minimax_single_thread(board, turn, depth);
-    }
+fn is_legal(r#move: &Move, board: &Board) -> bool {
+    let piece = match board.square_content(r#move.from_square()) {
+        Some(piece) => piece,
+        None => return false,
+    };
 
-    let moves_to_consider: Vec<Move> = player_moves(turn, board);
-    let best_move = moves_to_consider
-        .par_iter()
-        .map(|r#move| {
-            let mut cloned_board = *board;
-            cloned_board.apply_unchecked(&r#move);
-            cloned_board.next_turn();
+    if let Some(side) = board.castling_side(r#move) {
+        // Roszada: Sprawdzamy czy król nie przechodzi
+        // przez szachowane pole. Pole docelowe sprawdzamy
+        // też bez wieży, która mogła zasłaniać atak.
+        let (king_to, _) = castle_destinations(&piece.color, &side);
+        let enemy = board.occupied_by(&piece.color.enemy());
+        let occupied = board.occupied() & !square_bb(r#move.to_square());
+
+        squares(rank_span(r#move.from_square(), king_to))
+            .all(|square| !board.is_square_attacked(square, &piece.color.enemy()))
+            && board.attackers_to(king_to, occupied) & enemy == 0
+    } else {
+        // Nie-Roszada: Sprawdzamy czy po ruchu nie
+        // ma szacha. Planszy nie kopiujemy, wystarczy
+        // zajętość pól po ruchu.
+        let from = r#move.from_square();
+        let to = r#move.to_square();
+
+        let captured = if piece.kind_of_piece == KindOfPiece::Pawn
+            && r#move.from_file_number() != r#move.to_file_number()
+            && board.square_content(to).is_none()
+        {
+            // Bicie przelotem: zbity pion stoi obok.
+            square_bb(from - from % 8 + to % 8)
+        } else {
+            square_bb(to)
+        };
+        let occupied = (board.occupied() & !square_bb(from) & !captured) | square_bb(to);
 
-            // Forced stop.
-            if unsafe { STOP_ALL_THREADS.load(Ordering::SeqCst) } == true {
-                return (None, 0);
+        let king = if piece.kind_of_piece == KindOfPiece::King {
+            to
+        } else {
+            match board.king_square(&piece.color) {
+                Some(king) => king,
+                None => return true,
             }
+        };
 
-            (
-                Some(*r#move),
-                -minimax_multithreaded(&cloned_board, &turn.enemy(), depth - 1).1,
-            )
-        })
-        .reduce_with(|move1, move2| if move1.1 > move2.1 { move1 } else { move2 })
-        .unwrap();
-
-    // Forced stop.
-    if unsafe { STOP_ALL_THREADS.load(Ordering::SeqCst) } == true {
-        return (None, 0);
+        board.attackers_to(king, occupied) & board.occupied_by(&piece.color.enemy()) & !captured
+            == 0
     }
+}
 
-    best_move
+/// Limits that checks and pins put on the moves of one
+/// player, found once per position.
+struct Restrictions {
+    /// Squares pieces other than the king may move to: any
+    /// when not in check, the checker and the squares between
+    /// it and the king in check, none in double check.
+    check_mask: Bitboard,
+    /// For every square, the squares a piece standing there
+    /// may move to without exposing the king: the line up to
+    /// and including the pinner for pinned pieces, any otherwise.
+    pin_rays: [Bitboard; 64],
 }
 
-fn minimax_single_thread(board: &Board, turn: &Color, depth: u8) -> (Option<Move>, i32) {
-    // Forced stop.
-    if unsafe { STOP_ALL_THREADS.load(Ordering::SeqCst) } == true {
-        return (None, 0);
-    }
+impl Restrictions {
+    fn new(color: &Color, board: &Board) -> Self {
+        let mut restrictions = Restrictions {
+            check_mask: !0,
+            pin_rays: [!0; 64],
+        };
 
-    if depth == 0 {
-        let quality = board.eval();
+        let king = match board.king_square(color) {
+            Some(king) => king,
+            None => return restrictions,
+        };
+        let enemy = color.enemy();
+        let occupied = board.occupied();
+
+        let checkers = board.attackers_to(king, occupied) & board.occupied_by(&enemy);
+        restrictions.check_mask = match checkers.count_ones() {
+            0 => !0,
+            1 => checkers | between(king, checkers.trailing_zeros() as u8),
+            _ => 0,
+        };
 
-        if *turn == Color::White {
-            return (None, quality);
+        // Związana jest nasza figura, która jako jedyna
+        // stoi między królem a wrogą figurą dalekosiężną.
+        let queens = board.pieces(&enemy, &KindOfPiece::Queen);
+        let snipers = (rook_attacks(king, 0) & (board.pieces(&enemy, &KindOfPiece::Rook) | queens))
+            | (bishop_attacks(king, 0) & (board.pieces(&enemy, &KindOfPiece::Bishop) | queens));
+        for sniper in squares(snipers) {
+            let blockers = between(king, sniper) & occupied;
+            if blockers.count_ones() == 1 && blockers & board.occupied_by(color) != 0 {
+                restrictions.pin_rays[blockers.trailing_zeros() as usize] =
+                    between(king, sniper) | square_bb(sniper);
+            }
+        }
+
+        restrictions
+    }
+}
+
+/// Add moves from `square` to every square in `targets`,
+/// with every promotion for pawns reaching the last rank.
+fn push_moves(square: u8, targets: Bitboard, board: &Board, moves: &mut MoveList) {
+    let pawn = matches!(
+        board.square_content(square),
+        Some(piece) if piece.kind_of_piece == KindOfPiece::Pawn
+    );
+
+    let from = Field::from_square(square);
+    for target in squares(targets) {
+        let to = Field::from_square(target);
+
+        if pawn && (RANK_1 | RANK_8) & square_bb(target) != 0 {
+            for promotion in [
+                KindOfPiece::Queen,
+                KindOfPiece::Rook,
+                KindOfPiece::Bishop,
+                KindOfPiece::Knight,
+            ] {
+                moves.push(Move::build(from, to, Some(promotion)).unwrap());
+            }
         } else {
-            return (None, -quality);
+            moves.push(Move::build(from, to, None).unwrap());
         }
     }
+}
 
-    let moves_to_consider: Vec<Move> = player_moves(turn, board);
-    let mut rated_moves: Vec<(Move, i32)> = Vec::new();
-    for r#move in moves_to_consider.into_iter() {
-        let mut cloned_board = *board;
-        cloned_board.apply_unchecked(&r#move);
-        cloned_board.next_turn();
+/// Which moves a generator produces.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum MoveKind {
+    All,
+    /// Captures, en passant and promotions.
+    Captures,
+    /// Everything else, castling included.
+    Quiets,
+}
 
-        rated_moves.push((
-            r#move,
-            -minimax_single_thread(&cloned_board, &turn.enemy(), depth - 1).1,
-        ));
-    }
+/// Squares the piece on `square` can legally move to
+/// with a move of kind `kind`.
+fn legal_targets(
+    square: u8,
+    board: &Board,
+    restrictions: &Restrictions,
+    kind: MoveKind,
+) -> Bitboard {
+    let piece = match board.square_content(square) {
+        Some(piece) => piece,
+        None => return 0,
+    };
 
-    // Forced stop.
-    if unsafe { STOP_ALL_THREADS.load(Ordering::SeqCst) } == true {
-        return (None, 0);
+    // Dla pionów biciem jest też bicie przelotem i promocja.
+    let mut captures = board.occupied_by(&piece.color.enemy());
+    if piece.kind_of_piece == KindOfPiece::Pawn {
+        captures |= RANK_1 | RANK_8 | board.en_passant_square().map_or(0, square_bb);
     }
+    let targets = possible_moves_unchecked(square, board)
+        & match kind {
+            MoveKind::All => !0,
+            MoveKind::Captures => captures,
+            MoveKind::Quiets => !captures,
+        };
+    let enemy = board.occupied_by(&piece.color.enemy());
 
-    if rated_moves.len() == 0 {
-        // Check Mate
-        return (None, -1000000);
+    match piece.kind_of_piece {
+        KindOfPiece::King => {
+            // Króla nie ma na starym polu, więc nie
+            // zasłania ataków wzdłuż linii, po której ucieka.
+            let occupied = board.occupied() & !square_bb(square);
+            let mut legal: Bitboard = 0;
+            for target in squares(targets) {
+                let r#move =
+                    Move::build(Field::from_square(square), Field::from_square(target), None)
+                        .unwrap();
+
+                let safe = if board.castling_side(&r#move).is_some() {
+                    is_legal(&r#move, board)
+                } else {
+                    board.attackers_to(target, occupied) & enemy == 0
+                };
+                if safe {
+                    legal |= square_bb(target);
+                }
+            }
+            legal
+        }
+        KindOfPiece::Pawn => {
+            let mut legal =
+                targets & restrictions.check_mask & restrictions.pin_rays[square as usize];
+
+            // Bicie przelotem zdejmuje z linii dwa piony naraz,
+            // więc sprawdzamy je osobno.
+            if let Some(en_passant) = board.en_passant_square() {
+                legal &= !square_bb(en_passant);
+                let r#move = Move::build(
+                    Field::from_square(square),
+                    Field::from_square(en_passant),
+                    None,
+                )
+                .unwrap();
+                if targets & square_bb(en_passant) != 0 && is_legal(&r#move, board) {
+                    legal |= square_bb(en_passant);
+                }
+            }
+            legal
+        }
+        _ => targets & restrictions.check_mask & restrictions.pin_rays[square as usize],
     }
+}
 
-    let mut best_move = rated_moves[0];
-    for rated_move in rated_moves.into_iter() {
-        if rated_move.1 > best_move.1 {
-            best_move = rated_move;
-        }
+/// Legal moves of kind `kind` of player of color `color`.
+///
+/// Checks and pins are found once, so only king
+/// moves and en passant are checked one by one.
+fn generate(color: &Color, board: &Board, kind: MoveKind) -> MoveList {
+    let mut moves = MoveList::new();
+    let restrictions = Restrictions::new(color, board);
+
+    // Pod podwójnym szachem rusza się tylko król.
+    let pieces = if restrictions.check_mask == 0 {
+        board.pieces(color, &KindOfPiece::King)
+    } else {
+        board.occupied_by(color)
+    };
+
+    for square in squares(pieces) {
+        push_moves(
+            square,
+            legal_targets(square, board, &restrictions, kind),
+            board,
+            &mut moves,
+        );
     }
 
-    (Some(best_move.0), best_move.1)
+    moves
 }
 
-fn player_moves(color: &Color, board: &Board) -> Vec<Move> {
-    let mut moves: Vec<Move> = Vec::new();
+/// Legal moves of player of color `color`.
+pub fn player_moves(color: &Color, board: &Board) -> MoveList {
+    generate(color, board, MoveKind::All)
+}
 
-    for row in 1..=8 {
-        for file in 1..=8 {
-            let field = Field::build_unchecked(row, file);
-            if let Some(piece) = board.field_content(&field) {
-                if piece.color == *color {
-                    moves.append(&mut possible_moves(&field, board));
-                }
-            }
+/// Legal captures and promotions of player of color `color`.
+pub fn generate_captures(color: &Color, board: &Board) -> MoveList {
+    generate(color, board, MoveKind::Captures)
+}
+
+/// Legal moves of player of color `color`
+/// which neither capture nor promote.
+pub fn generate_quiets(color: &Color, board: &Board) -> MoveList {
+    generate(color, board, MoveKind::Quiets)
+}
+
+/// Legal moves of player of color `color`, who is in check.
+///
+/// The king may step away or capture the checker. Under
+/// a single check other pieces may also capture it or block
+/// the line to the king, unless they are pinned themselves.
+pub fn generate_evasions(color: &Color, board: &Board) -> MoveList {
+    debug_assert!(is_check(board, color));
+
+    let mut moves = MoveList::new();
+    let restrictions = Restrictions::new(color, board);
+    let king = match board.king_square(color) {
+        Some(king) => king,
+        None => return moves,
+    };
+
+    push_moves(
+        king,
+        legal_targets(king, board, &restrictions, MoveKind::All),
+        board,
+        &mut moves,
+    );
+
+    // Pod podwójnym szachem rusza się tylko król.
+    if restrictions.check_mask == 0 {
+        return moves;
+    }
+
+    // Związana figura nie może ani zbić szachującego,
+    // ani zasłonić króla.
+    let defenders = board.occupied_by(color) & !square_bb(king);
+    for square in squares(defenders) {
+        if restrictions.pin_rays[square as usize] != !0 {
+            continue;
         }
+
+        push_moves(
+            square,
+            legal_targets(square, board, &restrictions, MoveKind::All),
+            board,
+            &mut moves,
+        );
     }
 
     moves
 }
 
-// Tutaj nie sprawdzam czy pola przez które
-// przechodzi król nie są szachowane.
-fn can_castle_short(color: &Color, board: &Board) -> bool {
-    let row = if *color == Color::White { 1 } else { 8 };
-
-    *board.field_content(&Field::build_unchecked(row, 5))
-        == Some(Piece {
-            kind_of_piece: KindOfPiece::King,
-            color: *color,
-        })
-        && *board.field_content(&Field::build_unchecked(row, 6)) == None
-        && *board.field_content(&Field::build_unchecked(row, 7)) == None
-        && *board.field_content(&Field::build_unchecked(row, 8))
-            == Some(Piece {
-                kind_of_piece: KindOfPiece::Rook,
-                color: *color,
-            })
-        && board.can_castle(color, &Castle::Short)
+/// Squares from `a` to `b`, both included, on one rank.
+fn rank_span(a: u8, b: u8) -> Bitboard {
+    let (low, high) = (a.min(b), a.max(b));
+
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
 }
 
 // Tutaj nie sprawdzam czy pola przez które
 // przechodzi król nie są szachowane.
-fn can_castle_long(color: &Color, board: &Board) -> bool {
-    let row = if *color == Color::White { 1 } else { 8 };
-
-    *board.field_content(&Field::build_unchecked(row, 5))
-        == Some(Piece {
-            kind_of_piece: KindOfPiece::King,
-            color: *color,
-        })
-        && *board.field_content(&Field::build_unchecked(row, 4)) == None
-        && *board.field_content(&Field::build_unchecked(row, 3)) == None
-        && *board.field_content(&Field::build_unchecked(row, 2)) == None
-        && *board.field_content(&Field::build_unchecked(row, 1))
-            == Some(Piece {
-                kind_of_piece: KindOfPiece::Rook,
-                color: *color,
-            })
-        && board.can_castle(color, &Castle::Short)
-}
-
-fn king_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let to_filter = match (field.get_row(), field.get_file()) {
-        (2..=7, 2..=7) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row() - 1, field.get_file() + 1),
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() + 1),
-        ],
-        (2..=7, 1) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row() - 1, field.get_file() + 1),
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() + 1),
-        ],
-        (2..=7, 8) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-        ],
-        (1, 2..=7) => vec![
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() + 1),
-        ],
-        (8, 2..=7) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row() - 1, field.get_file() + 1),
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-        ],
-        (1, 1) => vec![
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() + 1),
-        ],
-        (1, 8) => vec![
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() + 1, field.get_file()),
-        ],
-        (8, 1) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row() - 1, field.get_file() + 1),
-            Field::build_unchecked(field.get_row(), field.get_file() + 1),
-        ],
-        (8, 8) => vec![
-            Field::build_unchecked(field.get_row() - 1, field.get_file() - 1),
-            Field::build_unchecked(field.get_row() - 1, field.get_file()),
-            Field::build_unchecked(field.get_row(), field.get_file() - 1),
-        ],
-
-        // Non-existent field.
-        _ => vec![],
+fn can_castle(color: &Color, side: &Castle, board: &Board) -> bool {
+    if !board.can_castle(color, side) {
+        return false;
+    }
+
+    let king = match board.king_square(color) {
+        Some(king) => king,
+        None => return false,
     };
+    let rank = king - king % 8;
+    let rook = rank + board.castle_rook_file(color, side);
+    let (king_to, rook_to) = castle_destinations(color, side);
+
+    // Wszystkie pola po drodze króla i wieży muszą być
+    // puste, nie licząc ich samych.
+    let path = rank_span(king, king_to) | rank_span(rook, rook_to);
+    let others = board.occupied() & !square_bb(king) & !square_bb(rook);
+
+    rank == king_to - king_to % 8
+        && board.pieces(color, &KindOfPiece::Rook) & square_bb(rook) != 0
+        && path & others == 0
+}
 
-    let color = match board.field_content(field) {
+fn king_moves_unchecked(square: u8, board: &Board) -> Bitboard {
+    let color = match board.square_content(square) {
         Some(piece) => piece.color,
         None => Color::White,
     };
-    let mut moves: Vec<Field> = Vec::new();
-    for field in to_filter.into_iter() {
-        match board.field_content(&field) {
-            Some(Piece {
-                kind_of_piece: _,
-                color: piece_color,
-            }) if *piece_color == color => {}
-            _ => {
-                moves.push(field);
-            }
-        }
-    }
+    let mut moves = king_attacks(square) & !board.occupied_by(&color);
 
     // Dodajemy roszady które mogą zostać
-    // wykonane.
-    if color == Color::White && *field == (Field::build_unchecked(1, 5)) {
-        if can_castle_short(&color, &board) {
-            moves.push(Field::build_unchecked(1, 7));
-        }
-
-        if can_castle_long(&color, &board) {
-            moves.push(Field::build_unchecked(1, 3));
+    // wykonane. Król bije własną wieżę.
+    for side in [Castle::Short, Castle::Long] {
+        if can_castle(&color, &side, board) {
+            moves |= square_bb(square - square % 8 + board.castle_rook_file(&color, &side));
         }
     }
 
-    if color == Color::Black && *field == (Field::build_unchecked(8, 5)) {
-        if can_castle_short(&color, &board) {
-            moves.push(Field::build_unchecked(8, 7));
+    moves
+}
+
+fn pawn_moves_unchecked(square: u8, board: &Board) -> Bitboard {
+    let mut moves: Bitboard = 0;
+    let color = match board.square_content(square) {
+        Some(piece) => piece.color,
+        None => Color::White,
+    };
+
+    let empty = !board.occupied();
+    let (next, next2, start_rank) = match color {
+        Color::White => (square + 8, square + 16, RANK_2),
+        Color::Black => (square.wrapping_sub(8), square.wrapping_sub(16), RANK_7),
+    };
+
+    if next < 64 && empty & square_bb(next) != 0 {
+        moves |= square_bb(next);
+        if start_rank & square_bb(square) != 0 && empty & square_bb(next2) != 0 {
+            moves |= square_bb(next2);
         }
+    }
 
-        if can_castle_long(&color, &board) {
-            moves.push(Field::build_unchecked(8, 3));
+    let mut capturable = board.occupied_by(&color.enemy());
+    if board.which_turn() == color {
+        if let Some(en_passant_square) = board.en_passant_square() {
+            capturable |= square_bb(en_passant_square);
         }
     }
 
-    moves
+    moves | (pawn_attacks(&color, square) & capturable)
 }
 
-fn go_in_dir(field: &Field, board: &Board, ns: i32, we: i32) -> Vec<Field> {
-    let mut moves: Vec<Field> = Vec::new();
-    let (file, row) = (field.get_file(), field.get_row());
-    let color = match board.field_content(&field) {
-        Some(piece) => piece.color,
-        None => Color::White, /* UB */
+/// Deepest ply for which killer moves are kept.
+pub const MAX_PLY: usize = 128;
+
+/// History scores are halved when one of them grows above this.
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+/// Score of promotions to a rook, bishop or knight among quiet
+/// moves, below any history score.
+const UNDERPROMOTION_SCORE: i32 = -1;
+
+/// Is move `move` a legal move of the player on move.
+///
+/// Used for moves which do not come from the generator,
+/// like the move stored in the transposition table.
+fn is_valid(r#move: &Move, board: &Board) -> bool {
+    let piece = match board.square_content(r#move.from_square()) {
+        Some(piece) if piece.color == board.which_turn() => piece,
+        _ => return false,
     };
 
-    for i in 1..=8 {
-        if let Some(field) = Field::build(row as i32 + i * ns, file as i32 + i * we) {
-            let field_content = *board.field_content(&field);
+    if possible_moves_unchecked(r#move.from_square(), board) & square_bb(r#move.to_square()) == 0 {
+        return false;
+    }
 
-            if field_content == None {
-                moves.push(field);
-                continue;
-            }
+    let promotes = piece.kind_of_piece == KindOfPiece::Pawn
+        && (RANK_1 | RANK_8) & square_bb(r#move.to_square()) != 0;
+    let valid_promotion = match r#move.promotion() {
+        None => !promotes,
+        Some(_) => promotes,
+    };
 
-            if field_content.unwrap().color == color {
-                break;
-            } else {
-                moves.push(field);
-                break;
-            }
+    valid_promotion && is_legal(r#move, board)
+}
+
+/// Two quiet moves per ply which recently caused a beta cutoff.
+///
+/// Shared by all search threads, like the transposition table.
+pub struct KillerTable {
+    slots: Vec<[AtomicU16; 2]>,
+}
+
+impl KillerTable {
+    pub fn new() -> Self {
+        let mut slots = Vec::with_capacity(MAX_PLY);
+        slots.resize_with(MAX_PLY, Default::default);
+
+        KillerTable { slots }
+    }
+
+    /// Killer moves at ply `ply`, the newest first.
+    pub fn get(&self, ply: usize) -> [Option<Move>; 2] {
+        let load = |slot: &AtomicU16| Move::from_code(slot.load(Ordering::Relaxed));
+
+        match self.slots.get(ply) {
+            Some([first, second]) => [load(first), load(second)],
+            None => [None, None],
         }
     }
 
-    moves
+    /// Remember that move `move` caused a cutoff at ply `ply`.
+    pub fn store(&self, ply: usize, r#move: &Move) {
+        if let Some([first, second]) = self.slots.get(ply) {
+            let previous = first.swap(r#move.code(), Ordering::Relaxed);
+            if previous != r#move.code() {
+                second.store(previous, Ordering::Relaxed);
+            }
+        }
+    }
 }
 
-fn queen_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let mut moves: Vec<Field> = Vec::new();
-
-    moves.append(&mut go_in_dir(field, board, 1, 0));
-    moves.append(&mut go_in_dir(field, board, 1, 1));
-    moves.append(&mut go_in_dir(field, board, 1, -1));
-    moves.append(&mut go_in_dir(field, board, 0, 1));
-    moves.append(&mut go_in_dir(field, board, 0, -1));
-    moves.append(&mut go_in_dir(field, board, -1, 0));
-    moves.append(&mut go_in_dir(field, board, -1, 1));
-    moves.append(&mut go_in_dir(field, board, -1, -1));
+impl Default for KillerTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    moves
+/// Score of every quiet move, indexed by color, from and to square,
+/// growing each time the move causes a beta cutoff.
+pub struct HistoryTable {
+    scores: Vec<AtomicI32>,
 }
 
-fn bishop_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let mut moves: Vec<Field> = Vec::new();
+impl HistoryTable {
+    pub fn new() -> Self {
+        let mut scores = Vec::with_capacity(2 * 64 * 64);
+        scores.resize_with(2 * 64 * 64, Default::default);
 
-    moves.append(&mut go_in_dir(field, board, 1, 1));
-    moves.append(&mut go_in_dir(field, board, -1, 1));
-    moves.append(&mut go_in_dir(field, board, -1, -1));
-    moves.append(&mut go_in_dir(field, board, 1, -1));
+        HistoryTable { scores }
+    }
 
-    moves
-}
+    fn index(color: &Color, r#move: &Move) -> usize {
+        64 * 64 * color.index() + 64 * r#move.from_square() as usize + r#move.to_square() as usize
+    }
 
-fn knight_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let to_filter = [
-        Field::build(field.get_row() as i32 - 1, field.get_file() as i32 - 2),
-        Field::build(field.get_row() as i32 - 2, field.get_file() as i32 - 1),
-        Field::build(field.get_row() as i32 - 2, field.get_file() as i32 + 1),
-        Field::build(field.get_row() as i32 - 1, field.get_file() as i32 + 2),
-        Field::build(field.get_row() as i32 + 1, field.get_file() as i32 + 2),
-        Field::build(field.get_row() as i32 + 2, field.get_file() as i32 + 1),
-        Field::build(field.get_row() as i32 + 2, field.get_file() as i32 - 1),
-        Field::build(field.get_row() as i32 + 1, field.get_file() as i32 - 2),
-    ];
+    /// History score of move `move` of player of color `color`.
+    pub fn get(&self, color: &Color, r#move: &Move) -> i32 {
+        self.scores[Self::index(color, r#move)].load(Ordering::Relaxed)
+    }
 
-    let color = match board.field_content(field) {
-        Some(piece) => piece.color,
-        None => Color::White,
-    };
-    let mut moves: Vec<Field> = Vec::new();
-    for field in to_filter.into_iter() {
-        if let Some(field) = field {
-            match board.field_content(&field) {
-                Some(Piece {
-                    kind_of_piece: _,
-                    color: piece_color,
-                }) if *piece_color == color => {}
-                _ => {
-                    moves.push(field);
-                }
+    /// Reward move `move` for a cutoff at depth `depth`.
+    pub fn store(&self, color: &Color, r#move: &Move, depth: u8) {
+        let bonus = depth as i32 * depth as i32;
+        let score = self.scores[Self::index(color, r#move)].fetch_add(bonus, Ordering::Relaxed);
+
+        // Stare wyniki tracą na znaczeniu.
+        if score + bonus > HISTORY_LIMIT {
+            for score in self.scores.iter() {
+                score.store(score.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
             }
         }
     }
-
-    moves
 }
 
-fn rook_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let mut moves: Vec<Field> = Vec::new();
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    moves.append(&mut go_in_dir(field, board, 1, 0));
-    moves.append(&mut go_in_dir(field, board, -1, 0));
-    moves.append(&mut go_in_dir(field, board, 0, 1));
-    moves.append(&mut go_in_dir(field, board, 0, -1));
+/// Order in which `MovePicker` hands out moves.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Stage {
+    HashMove,
+    Generate,
+    Captures,
+    Killers,
+    Quiets,
+    Done,
+}
 
-    moves
+/// Legal moves of the player on move, best looking first.
+///
+/// The hash move is tried before anything is generated. Then come
+/// captures and queen promotions by MVV-LVA, the killer moves and
+/// finally the quiet moves by their history score.
+///
+/// The picker does not hold the position, so that the search can
+/// make and unmake moves between picks. Each pick must be given
+/// the same position.
+pub struct MovePicker<'a> {
+    history: &'a HistoryTable,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    quiets_wanted: bool,
+    stage: Stage,
+    captures: Vec<(Move, i32)>,
+    quiets: Vec<(Move, i32)>,
 }
 
-fn pawn_moves_unchecked(field: &Field, board: &Board) -> Vec<Field> {
-    let mut moves: Vec<Field> = Vec::new();
-    let color = match board.field_content(field) {
-        Some(piece) => piece.color,
-        None => Color::White,
-    };
+impl<'a> MovePicker<'a> {
+    /// Picker over all legal moves.
+    pub fn new(
+        hash_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        history: &'a HistoryTable,
+    ) -> Self {
+        MovePicker {
+            history,
+            hash_move,
+            killers,
+            quiets_wanted: true,
+            stage: Stage::HashMove,
+            captures: Vec::new(),
+            quiets: Vec::new(),
+        }
+    }
 
-    let next_row = match color {
-        Color::White => field.get_row() as i32 + 1,
-        Color::Black => field.get_row() as i32 - 1,
-    };
-    let next2_row = match color {
-        Color::White => field.get_row() as i32 + 2,
-        Color::Black => field.get_row() as i32 - 2,
-    };
+    /// Picker over captures and queen promotions only.
+    pub fn captures(history: &'a HistoryTable) -> Self {
+        MovePicker {
+            quiets_wanted: false,
+            ..Self::new(None, [None, None], history)
+        }
+    }
 
-    if let Some(next_field) = Field::build(next_row, field.get_file() as i32) {
-        if *board.field_content(&next_field) == None {
-            moves.push(next_field);
-            if (color == Color::White && field.get_row() == 2)
-                || (color == Color::Black && field.get_row() == 7)
-            {
-                if let Some(next2_field) = Field::build(next2_row, field.get_file() as i32) {
-                    if *board.field_content(&next2_field) == None {
-                        moves.push(next2_field);
-                    }
+    fn generate(&mut self, board: &Board) {
+        let color = board.which_turn();
+
+        // Pod szachem wystarczy wygenerować obrony
+        // i podzielić je na bicia i ciche ruchy.
+        let (captures, quiets) = if is_check(board, &color) {
+            let mut captures = MoveList::new();
+            let mut quiets = MoveList::new();
+            for r#move in generate_evasions(&color, board) {
+                if captured_kind(board, &r#move).is_some() || r#move.promotion().is_some() {
+                    captures.push(r#move);
+                } else {
+                    quiets.push(r#move);
                 }
             }
-        }
-    }
+            (captures, quiets)
+        } else if self.quiets_wanted {
+            (
+                generate_captures(&color, board),
+                generate_quiets(&color, board),
+            )
+        } else {
+            (generate_captures(&color, board), MoveList::new())
+        };
+
+        for r#move in captures {
+            if Some(r#move) == self.hash_move {
+                continue;
+            }
 
-    let nw_field = Field::build(next_row as i32, field.get_file() as i32 - 1);
-    if let Some(field) = nw_field {
-        if let Some(piece) = *board.field_content(&field) {
-            if piece.color != color {
-                moves.push(field);
+            let promotion = r#move.promotion();
+            if promotion.is_some() && promotion != Some(KindOfPiece::Queen) {
+                // Podpromocje rzadko są coś warte,
+                // sprawdzamy je po wszystkich cichych ruchach.
+                if self.quiets_wanted {
+                    self.quiets.push((r#move, UNDERPROMOTION_SCORE));
+                }
+            } else {
+                // MVV-LVA: najpierw najcenniejsza ofiara,
+                // potem najtańszy napastnik.
+                let captured = captured_kind(board, &r#move);
+                let attacker = board.square_content(r#move.from_square()).unwrap();
+                let score = captured.map_or(0, |captured| kind_value(&captured))
+                    + promotion.map_or(0, |promotion| kind_value(&promotion))
+                    - attacker.kind_of_piece.index() as i32;
+                self.captures.push((r#move, score));
             }
         }
-    }
 
-    let ne_field = Field::build(next_row as i32, field.get_file() as i32 + 1);
-    if let Some(field) = ne_field {
-        if let Some(piece) = *board.field_content(&field) {
-            if piece.color != color {
-                moves.push(field);
+        if !self.quiets_wanted {
+            return;
+        }
+
+        for r#move in quiets {
+            if Some(r#move) != self.hash_move {
+                self.quiets
+                    .push((r#move, self.history.get(&color, &r#move)));
             }
         }
     }
 
-    let e_field = Field::build(field.get_row() as i32, field.get_file() as i32 + 1);
-    if let Some(field) = e_field {
-        if board.can_en_passant(field.get_file())
-            && ((color == Color::White && field.get_row() == 5)
-                || (color == Color::Black && field.get_row() == 4))
-        {
-            if let Some(piece) = *board.field_content(&field) {
-                if piece.color != color {
-                    moves.push(field);
+    /// Take the best scored move out of `moves`.
+    fn pick_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+        let best = moves
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, score))| *score)
+            .map(|(i, _)| i)?;
+
+        Some(moves.swap_remove(best).0)
+    }
+
+    /// Next move to search in position `board`.
+    pub fn next_move(&mut self, board: &Board) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::Generate;
+
+                    // Ruch z tablicy transpozycji mógł należeć
+                    // do innej pozycji o tym samym kluczu.
+                    match self.hash_move {
+                        Some(r#move) if is_valid(&r#move, board) => return Some(r#move),
+                        _ => self.hash_move = None,
+                    }
+                }
+                Stage::Generate => {
+                    self.generate(board);
+                    self.stage = Stage::Captures;
                 }
+                Stage::Captures => match Self::pick_best(&mut self.captures) {
+                    Some(r#move) => return Some(r#move),
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    // Zabójca musi być cichym ruchem legalnym w tej pozycji.
+                    for killer in self.killers.iter_mut().filter_map(Option::take) {
+                        if let Some(i) =
+                            self.quiets.iter().position(|(r#move, _)| *r#move == killer)
+                        {
+                            return Some(self.quiets.swap_remove(i).0);
+                        }
+                    }
+
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match Self::pick_best(&mut self.quiets) {
+                    Some(r#move) => return Some(r#move),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
             }
         }
     }
 
-    let w_field = Field::build(field.get_row() as i32, field.get_file() as i32 - 1);
-    if let Some(field) = w_field {
-        if board.can_en_passant(field.get_file())
-            && ((color == Color::White && field.get_row() == 5)
-                || (color == Color::Black && field.get_row() == 4))
-        {
-            if let Some(piece) = *board.field_content(&field) {
-                if piece.color != color {
-                    moves.push(field);
-                }
+    /// All remaining moves in position `board`,
+    /// for when the position does not change in between.
+    pub fn moves<'b>(mut self, board: &'b Board) -> impl Iterator<Item = Move> + 'b
+    where
+        'a: 'b,
+    {
+        std::iter::from_fn(move || self.next_move(board))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::FENString;
+    use super::*;
+
+    #[test]
+    fn test_move_picker() {
+        // Czarny hetman na d5 i skoczek na b5 do zbicia.
+        let board =
+            Board::try_from(FENString::try_from("4k3/8/8/1n1q4/8/2N5/8/R3K3 w - -").unwrap())
+                .unwrap();
+        let history = HistoryTable::new();
+        let hash_move = Move::try_from("a1a7").unwrap();
+        let killer = Move::try_from("e1f2").unwrap();
+
+        let picked: Vec<Move> = MovePicker::new(Some(hash_move), [Some(killer), None], &history)
+            .moves(&board)
+            .collect();
+        let generated = player_moves(&Color::White, &board);
+        assert_eq!(picked.len(), generated.len());
+        assert!(generated.iter().all(|r#move| picked.contains(r#move)));
+
+        assert_eq!(picked[0], hash_move);
+        // Najpierw bicie hetmana, potem skoczka.
+        assert_eq!(picked[1], Move::try_from("c3d5").unwrap());
+        assert_eq!(picked[2], Move::try_from("c3b5").unwrap());
+        assert_eq!(picked[3], killer);
+
+        // Ruch spoza pozycji nie jest zwracany.
+        let picked: Vec<Move> = MovePicker::new(
+            Some(Move::try_from("e2e4").unwrap()),
+            [None, None],
+            &history,
+        )
+        .moves(&board)
+        .collect();
+        assert!(!picked.contains(&Move::try_from("e2e4").unwrap()));
+
+        let captures: Vec<Move> = MovePicker::captures(&history).moves(&board).collect();
+        assert_eq!(
+            captures,
+            vec![
+                Move::try_from("c3d5").unwrap(),
+                Move::try_from("c3b5").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pins() {
+        let moves = |fen| {
+            let board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+            player_moves(&board.which_turn(), &board)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+        };
+
+        // Wieża związana na kolumnie e chodzi tylko po niej.
+        let generated = moves("4r1k1/8/8/8/8/8/4R3/4K3 w - -");
+        let rook: Vec<&String> = generated.iter().filter(|m| m.starts_with("e2")).collect();
+        assert_eq!(rook.len(), 6);
+        assert!(generated.contains(&"e2e8".to_owned()));
+        assert!(!generated.contains(&"e2d2".to_owned()));
+
+        // Związany skoczek nie ma ruchów.
+        let generated = moves("6k1/8/8/8/1b6/8/3N4/4K3 w - -");
+        assert!(!generated.iter().any(|m| m.starts_with("d2")));
+
+        // Bicie przelotem odsłoniłoby szach wzdłuż piątego rzędu.
+        let generated = moves("8/8/8/K2pP2r/8/8/8/7k w - d6");
+        assert!(!generated.contains(&"e5d6".to_owned()));
+        assert!(generated.contains(&"e5e6".to_owned()));
+
+        // Pod podwójnym szachem rusza się tylko król.
+        let generated = moves("4k3/8/8/8/1b6/8/4r3/R3K3 w - -");
+        assert!(generated.iter().all(|m| m.starts_with("e1")));
+    }
+
+    #[test]
+    fn test_generators() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq -",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6",
+            "4k3/8/8/8/1b6/8/4r3/R3K3 w - -",
+            "4k3/8/8/8/8/1N6/3P4/r3K3 w - -",
+            "4k3/4r3/8/8/1b6/8/3N4/4K3 w - -",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3",
+        ] {
+            let board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+            let color = board.which_turn();
+            let all = player_moves(&color, &board);
+            let captures = generate_captures(&color, &board);
+            let quiets = generate_quiets(&color, &board);
+
+            // Bicia i ciche ruchy dzielą wszystkie ruchy na dwie części.
+            assert_eq!(captures.len() + quiets.len(), all.len(), "{}", fen);
+            assert!(all
+                .iter()
+                .all(|r#move| captures.contains(r#move) != quiets.contains(r#move)));
+            assert!(captures.iter().all(|r#move| {
+                captured_kind(&board, r#move).is_some() || r#move.promotion().is_some()
+            }));
+
+            if is_check(&board, &color) {
+                let evasions = generate_evasions(&color, &board);
+                assert_eq!(evasions.len(), all.len(), "{}", fen);
+                assert!(all.iter().all(|r#move| evasions.contains(r#move)));
             }
         }
     }
 
-    moves
+    #[test]
+    fn test_underpromotions() {
+        let board =
+            Board::try_from(FENString::try_from("k7/4P3/8/8/8/8/8/4K3 w - -").unwrap()).unwrap();
+        let history = HistoryTable::new();
+
+        // Promocja na hetmana razem z biciami,
+        // pozostałe po wszystkich cichych ruchach.
+        let picked: Vec<String> = MovePicker::new(None, [None, None], &history)
+            .moves(&board)
+            .map(|r#move| r#move.to_string())
+            .collect();
+        assert_eq!(picked.len(), 9);
+        assert_eq!(picked[0], "e7e8q");
+        let mut last: Vec<&str> = picked[6..].iter().map(String::as_str).collect();
+        last.sort();
+        assert_eq!(last, ["e7e8b", "e7e8n", "e7e8r"]);
+
+        let captures: Vec<Move> = MovePicker::captures(&history).moves(&board).collect();
+        assert_eq!(captures, vec![Move::try_from("e7e8q").unwrap()]);
+    }
+
+    #[test]
+    fn test_castling() {
+        let board =
+            Board::try_from(FENString::try_from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap())
+                .unwrap();
+        let moves = player_moves(&Color::White, &board);
+
+        // Roszady generowane są jako bicie własnej wieży.
+        let short = move_from_uci(&board, "e1g1", false).unwrap();
+        let long = move_from_uci(&board, "e1c1", false).unwrap();
+        assert_eq!(short, Move::try_from("e1h1").unwrap());
+        assert_eq!(long, Move::try_from("e1a1").unwrap());
+        assert!(moves.contains(&short));
+        assert!(moves.contains(&long));
+        assert_eq!(captured_kind(&board, &short), None);
+
+        assert_eq!(move_to_uci(&board, &short, false), "e1g1");
+        assert_eq!(move_to_uci(&board, &short, true), "e1h1");
+        assert_eq!(move_from_uci(&board, "e1h1", true), Ok(short));
+        assert_eq!(
+            move_to_uci(&board, &Move::try_from("e1f1").unwrap(), false),
+            "e1f1"
+        );
+
+        // Chess960: wieża f1 zasłania króla na g1 przed wieżą
+        // na a1, po roszadzie już nie.
+        let board =
+            Board::try_from(FENString::try_from("1k6/8/8/8/8/8/8/r3KR2 w F - 0 1").unwrap())
+                .unwrap();
+        let moves = player_moves(&Color::White, &board);
+        assert!(!moves.contains(&Move::try_from("e1f1").unwrap()));
+
+        let board =
+            Board::try_from(FENString::try_from("1k6/8/8/8/8/8/8/4KR1r w F - 0 1").unwrap())
+                .unwrap();
+        let moves = player_moves(&Color::White, &board);
+        assert!(!moves.contains(&Move::try_from("e1f1").unwrap()));
+
+        let board =
+            Board::try_from(FENString::try_from("1k6/8/8/8/8/8/8/3RK1R1 w GD - 0 1").unwrap())
+                .unwrap();
+        let moves = player_moves(&Color::White, &board);
+        assert!(moves.contains(&Move::try_from("e1g1").unwrap()));
+        assert!(moves.contains(&Move::try_from("e1d1").unwrap()));
+    }
 }
@@ -0,0 +1,56 @@
+use super::board::Board;
+use super::book::Move;
+
+/// Number of leaf nodes of the tree of legal moves
+/// of depth `depth` growing from position `board`.
+///
+/// Comparing it with known values for standard
+/// positions tests the move generator.
+pub fn perft(board: &mut Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = board.legal_moves();
+
+    // Na ostatnim poziomie wystarczy policzyć ruchy.
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes: u64 = 0;
+    for r#move in moves.iter() {
+        let undo = board.make_move(r#move);
+        nodes += perft(board, depth - 1);
+        board.unmake_move(r#move, undo);
+    }
+
+    nodes
+}
+
+/// `perft` split by the first move, which helps
+/// finding the move the generator gets wrong,
+/// together with the total number of nodes.
+///
+/// At depth 0 there is no first move, but the
+/// position itself still counts as one node.
+pub fn divide(board: &mut Board, depth: u8) -> (Vec<(Move, u64)>, u64) {
+    if depth == 0 {
+        return (Vec::new(), 1);
+    }
+
+    let moves: Vec<(Move, u64)> = board
+        .legal_moves()
+        .into_iter()
+        .map(|r#move| {
+            let undo = board.make_move(&r#move);
+            let nodes = perft(board, depth - 1);
+            board.unmake_move(&r#move, undo);
+
+            (r#move, nodes)
+        })
+        .collect();
+    let nodes = moves.iter().map(|(_, nodes)| nodes).sum();
+
+    (moves, nodes)
+}
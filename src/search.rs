@@ -0,0 +1,1161 @@
+use std::cmp::Reverse;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use super::bitboard::square_bb;
+use super::bitboard::RANK_2;
+use super::bitboard::RANK_7;
+use super::board::Board;
+use super::board::Color;
+use super::board::KindOfPiece;
+use super::book::Move;
+use super::moves::captured_kind;
+use super::moves::is_check;
+use super::moves::move_to_uci;
+use super::moves::player_moves;
+use super::moves::HistoryTable;
+use super::moves::KillerTable;
+use super::moves::MovePicker;
+use super::tables::kind_value;
+use super::tt::Bound;
+use super::tt::Entry;
+use super::tt::TranspositionTable;
+
+use rayon::prelude::*;
+
+/// Bound larger than any score the search can return.
+const INFINITY: i32 = 2_000_000;
+
+/// Score of a player who is checkmated on the board.
+///
+/// Being mated `ply` plies from the root scores `-(MATE - ply)`,
+/// so that the search prefers the quickest mate and the slowest
+/// loss.
+const MATE: i32 = 1_000_000;
+
+/// Scores further than this from zero mean a forced mate.
+const MATE_BOUND: i32 = MATE - 1000;
+
+/// Score of a drawn position.
+const DRAW: i32 = 0;
+
+/// Margin for delta pruning in quiescence search.
+const DELTA_MARGIN: i32 = 200;
+
+/// Half-width of the first aspiration window.
+const ASPIRATION_WINDOW: i32 = 50;
+
+/// Shallowest iteration searched with an aspiration window.
+const ASPIRATION_MIN_DEPTH: u8 = 5;
+
+/// Shallowest node where null-move pruning is tried.
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+
+/// Shallowest node where late moves are reduced.
+const LMR_MIN_DEPTH: u8 = 3;
+
+/// Number of moves searched at full depth before reducing.
+const LMR_FULL_DEPTH_MOVES: usize = 3;
+
+/// Deepest node where futility pruning is used.
+const FUTILITY_MAX_DEPTH: u8 = 3;
+
+/// Futility margin for every ply of remaining depth.
+const FUTILITY_MARGIN: i32 = 150;
+
+/// Shallowest node where singular extensions are tried.
+const SINGULAR_MIN_DEPTH: u8 = 6;
+
+/// How much worse than the hash move, for every ply of depth,
+/// all other moves must be for the hash move to be singular.
+const SINGULAR_MARGIN: i32 = 2;
+
+/// Deepest ply where moves are still extended, so that
+/// a long series of checks cannot run the search away.
+const MAX_EXTENSION_PLY: usize = 64;
+
+/// Forward pruning techniques used by the search.
+///
+/// Every one of them can be switched off
+/// through UCI options, to compare the results.
+/// `chess960` only changes how castling is printed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SearchOptions {
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub futility_pruning: bool,
+    pub seventh_rank_extension: bool,
+    pub chess960: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            null_move: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            seventh_rank_extension: true,
+            chess960: false,
+        }
+    }
+}
+
+pub static STOP_ALL_THREADS: AtomicBool = AtomicBool::new(true);
+
+fn stopped() -> bool {
+    STOP_ALL_THREADS.load(Ordering::SeqCst)
+}
+
+/// State shared by all threads searching one position.
+struct Context<'a> {
+    tt: &'a TranspositionTable,
+    options: SearchOptions,
+    killers: KillerTable,
+    history: HistoryTable,
+}
+
+impl<'a> Context<'a> {
+    fn new(tt: &'a TranspositionTable, options: &SearchOptions) -> Self {
+        Context {
+            tt,
+            options: *options,
+            killers: KillerTable::new(),
+            history: HistoryTable::new(),
+        }
+    }
+
+    /// Moves of the player on move in position `board`
+    /// at ply `ply`, in the order they should be searched.
+    fn move_picker(&self, board: &Board, ply: usize) -> MovePicker<'_> {
+        let hash_move = self
+            .tt
+            .probe(board.hash())
+            .and_then(|entry| entry.best_move);
+
+        MovePicker::new(hash_move, self.killers.get(ply), &self.history)
+    }
+
+    /// Remember quiet move `move` which caused a cutoff.
+    fn store_cutoff(&self, board: &Board, r#move: &Move, depth: u8, ply: usize) {
+        if captured_kind(board, r#move).is_none() && r#move.promotion().is_none() {
+            self.killers.store(ply, r#move);
+            self.history.store(&board.which_turn(), r#move, depth);
+        }
+    }
+
+    /// How many plies less than usual to search move `move`,
+    /// the `index`-th one tried in position `board`.
+    ///
+    /// Late quiet moves rarely turn out best, so they are
+    /// searched shallower, unless they have a good history.
+    fn reduction(&self, board: &Board, r#move: &Move, index: usize, depth: u8) -> u8 {
+        if !self.options.late_move_reductions
+            || depth < LMR_MIN_DEPTH
+            || index < LMR_FULL_DEPTH_MOVES
+            || captured_kind(board, r#move).is_some()
+            || r#move.promotion().is_some()
+            || is_check(board, &board.which_turn())
+        {
+            return 0;
+        }
+
+        let mut reduction = if index < 2 * LMR_FULL_DEPTH_MOVES {
+            1
+        } else {
+            2
+        };
+        if self.history.get(&board.which_turn(), r#move) > 0 {
+            reduction -= 1;
+        }
+
+        // Zawsze zostawiamy przynajmniej jeden pełny ruch.
+        reduction.min(depth - 2)
+    }
+
+    /// How many plies more than usual to search the move
+    /// just made in position `board`, which gave check if
+    /// `gives_check` and was found singular if `singular`.
+    fn extension(
+        &self,
+        board: &Board,
+        r#move: &Move,
+        gives_check: bool,
+        singular: bool,
+        ply: usize,
+    ) -> u8 {
+        if ply >= MAX_EXTENSION_PLY {
+            return 0;
+        }
+
+        // Pion wchodzący na przedostatnią linię.
+        let seventh_rank = match board.which_turn() {
+            Color::White => RANK_2,
+            Color::Black => RANK_7,
+        };
+        let pawn_push = self.options.seventh_rank_extension
+            && board.pieces(&board.which_turn().enemy(), &KindOfPiece::Pawn)
+                & seventh_rank
+                & square_bb(r#move.to_square())
+                != 0;
+
+        (gives_check || singular || pawn_push) as u8
+    }
+}
+
+/// Best move for the player on move, or `None`
+/// when that player is checkmated or stalemated.
+pub fn get_move(board: &Board, tt: &TranspositionTable, options: &SearchOptions) -> Option<Move> {
+    let legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        return None;
+    }
+
+    let start = Instant::now();
+    let mut board = board.clone();
+    let mut r#move: Option<Move> = None;
+    let mut quality: i32 = 0;
+    let context = Context::new(tt, options);
+
+    for depth in 2..100 {
+        (r#move, quality) = match aspiration_search(&mut board, depth, quality, &context) {
+            Some((r#move, quality)) => (Some(r#move), quality),
+            None => break,
+        };
+
+        let mut line = board.clone();
+        let pv = principal_variation(&board, depth, tt)
+            .iter()
+            .map(|r#move| {
+                let text = move_to_uci(&line, r#move, options.chess960);
+                line.make_move(r#move);
+                text
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        println!(
+            "info depth {} score {} time {} pv {}",
+            depth,
+            uci_score(quality),
+            start.elapsed().as_millis(),
+            pv
+        );
+    }
+
+    // Przerwane przed końcem pierwszej iteracji:
+    // lepszy jakikolwiek legalny ruch niż żaden.
+    Some(r#move.unwrap_or(legal_moves[0]))
+}
+
+/// Score `quality` in UCI notation: in centipawns,
+/// or in moves to mate when a mate has been found.
+fn uci_score(quality: i32) -> String {
+    if quality >= MATE_BOUND {
+        format!("mate {}", (MATE - quality + 1) / 2)
+    } else if quality <= -MATE_BOUND {
+        format!("mate -{}", (MATE + quality) / 2)
+    } else {
+        format!("cp {}", quality)
+    }
+}
+
+/// Search the root in a window around `previous`, the score
+/// of the previous iteration, widening it until the score fits.
+fn aspiration_search(
+    board: &mut Board,
+    depth: u8,
+    previous: i32,
+    context: &Context,
+) -> Option<(Move, i32)> {
+    let mut delta = ASPIRATION_WINDOW;
+    let (mut alpha, mut beta) = if depth < ASPIRATION_MIN_DEPTH {
+        (-INFINITY, INFINITY)
+    } else {
+        (previous - delta, previous + delta)
+    };
+
+    loop {
+        let (best_move, quality) = alpha_beta_multithreaded(board, depth, 0, alpha, beta, context);
+
+        // Forced stop.
+        let best_move = best_move?;
+
+        if quality <= alpha {
+            alpha = (quality - delta).max(-INFINITY);
+        } else if quality >= beta {
+            beta = (quality + delta).min(INFINITY);
+        } else {
+            return Some((best_move, quality));
+        }
+
+        delta *= 2;
+    }
+}
+
+/// Best line found by the search, read from the transposition table.
+fn principal_variation(board: &Board, depth: u8, tt: &TranspositionTable) -> Vec<Move> {
+    let mut board = board.clone();
+    let mut pv: Vec<Move> = Vec::new();
+
+    while pv.len() < depth as usize {
+        let r#move = match tt.probe(board.hash()).and_then(|entry| entry.best_move) {
+            Some(r#move) => r#move,
+            None => break,
+        };
+
+        // Wpis mógł zostać nadpisany przez inną pozycję.
+        if !player_moves(&board.which_turn(), &board).contains(&r#move) {
+            break;
+        }
+
+        board.make_move(&r#move);
+        pv.push(r#move);
+    }
+
+    pv
+}
+
+/// Score of move `move`, the `index`-th one tried,
+/// searched with window `alpha`..`beta`.
+///
+/// Only the first move gets the full window. The others are
+/// first searched with a null window just to show they are
+/// worse, and searched again only when that turns out wrong.
+///
+/// In a node where futility pruning applies, `static_eval` is its
+/// evaluation. A quiet move which cannot raise it to `alpha` is
+/// not searched at all, and the result is `None`.
+#[allow(clippy::too_many_arguments)]
+fn principal_variation_search(
+    board: &mut Board,
+    r#move: &Move,
+    index: usize,
+    singular: bool,
+    depth: u8,
+    ply: usize,
+    alpha: i32,
+    beta: i32,
+    static_eval: Option<i32>,
+    context: &Context,
+) -> Option<i32> {
+    let quiet = captured_kind(board, r#move).is_none() && r#move.promotion().is_none();
+    let reduction = context.reduction(board, r#move, index, depth);
+    let undo = board.make_move(r#move);
+    let gives_check = is_check(board, &board.which_turn());
+    let extension = context.extension(board, r#move, gives_check, singular, ply);
+    let new_depth = depth - 1 + extension;
+
+    // Futility pruning: cichy ruch nie podniesie
+    // oceny do alfy tak blisko liści.
+    if let Some(static_eval) = static_eval {
+        if index > 0
+            && quiet
+            && extension == 0
+            && static_eval + FUTILITY_MARGIN * depth as i32 <= alpha
+        {
+            board.unmake_move(r#move, undo);
+            return None;
+        }
+    }
+
+    // Ruch przedłużony albo dający szacha nie jest skracany.
+    let reduction = if gives_check || extension > 0 {
+        0
+    } else {
+        reduction
+    };
+
+    let mut quality = if index == 0 {
+        -alpha_beta_multithreaded(board, new_depth, ply + 1, -beta, -alpha, context).1
+    } else {
+        -alpha_beta_multithreaded(
+            board,
+            new_depth - reduction,
+            ply + 1,
+            -alpha - 1,
+            -alpha,
+            context,
+        )
+        .1
+    };
+
+    if reduction > 0 && quality > alpha {
+        quality =
+            -alpha_beta_multithreaded(board, new_depth, ply + 1, -alpha - 1, -alpha, context).1;
+    }
+
+    if index > 0 && quality > alpha && quality < beta {
+        quality = -alpha_beta_multithreaded(board, new_depth, ply + 1, -beta, -alpha, context).1;
+    }
+
+    board.unmake_move(r#move, undo);
+
+    Some(quality)
+}
+
+/// Score proving the node searched at depth `depth` with upper
+/// bound `beta` fails high, found by letting the opponent move twice.
+///
+/// If the opponent cannot get under `beta` even then, a real move
+/// would not help him either. This fails in zugzwang, so it is not
+/// tried when the player on move has nothing but pawns.
+fn null_move_cutoff(
+    board: &mut Board,
+    depth: u8,
+    ply: usize,
+    beta: i32,
+    context: &Context,
+) -> Option<i32> {
+    let color = board.which_turn();
+    let pieces = board.pieces(&color, &KindOfPiece::Knight)
+        | board.pieces(&color, &KindOfPiece::Bishop)
+        | board.pieces(&color, &KindOfPiece::Rook)
+        | board.pieces(&color, &KindOfPiece::Queen);
+
+    if !context.options.null_move
+        || ply == 0
+        || depth < NULL_MOVE_MIN_DEPTH
+        || pieces == 0
+        || beta.abs() >= MATE / 2
+        || is_check(board, &color)
+        || eval_relative(board) < beta
+    {
+        return None;
+    }
+
+    let reduction = if depth > 6 { 3 } else { 2 };
+    let undo = board.make_null_move();
+    let quality = -alpha_beta_multithreaded(
+        board,
+        depth - 1 - reduction,
+        ply + 1,
+        -beta,
+        -beta + 1,
+        context,
+    )
+    .1;
+    board.unmake_null_move(undo);
+
+    // Forced stop.
+    if stopped() {
+        return None;
+    }
+
+    // Mat znaleziony po pasie nie jest prawdziwy.
+    match quality {
+        quality if quality >= MATE / 2 => Some(beta),
+        quality if quality >= beta => Some(quality),
+        _ => None,
+    }
+}
+
+/// Hash move of position `board` searched at depth `depth`,
+/// if it is much better than every other move.
+///
+/// Such a move is searched one ply deeper, since the
+/// whole line depends on it.
+fn singular_move(board: &mut Board, depth: u8, ply: usize, context: &Context) -> Option<Move> {
+    if ply == 0 || ply >= MAX_EXTENSION_PLY || depth < SINGULAR_MIN_DEPTH {
+        return None;
+    }
+
+    let entry = context.tt.probe(board.hash())?;
+    let hash_move = entry.best_move?;
+    if entry.bound == Bound::Upper || entry.depth + 3 < depth || entry.score.abs() >= MATE / 2 {
+        return None;
+    }
+
+    let moves_to_consider = player_moves(&board.which_turn(), board);
+    if !moves_to_consider.contains(&hash_move) {
+        return None;
+    }
+
+    // Wszystkie inne ruchy, przeszukane płycej,
+    // muszą być wyraźnie gorsze od ruchu z tablicy.
+    let singular_beta = entry.score - SINGULAR_MARGIN * depth as i32;
+    for r#move in moves_to_consider {
+        if r#move == hash_move {
+            continue;
+        }
+
+        let undo = board.make_move(&r#move);
+        let quality = -alpha_beta_multithreaded(
+            board,
+            depth / 2,
+            ply + 1,
+            -singular_beta,
+            -singular_beta + 1,
+            context,
+        )
+        .1;
+        board.unmake_move(&r#move, undo);
+
+        if stopped() || quality >= singular_beta {
+            return None;
+        }
+    }
+
+    Some(hash_move)
+}
+
+/// Evaluation from the point of view of the player on move.
+fn eval_relative(board: &Board) -> i32 {
+    let quality = board.eval();
+
+    if board.which_turn() == Color::White {
+        quality
+    } else {
+        -quality
+    }
+}
+
+/// Window `alpha`..`beta` narrowed to the scores
+/// which are still possible at ply `ply`.
+///
+/// Even mating right now scores no more than `MATE - ply - 1`,
+/// and being mated here scores no less than `-(MATE - ply)`.
+fn mate_distance_window(ply: usize, alpha: i32, beta: i32) -> (i32, i32) {
+    (
+        alpha.max(-MATE + ply as i32),
+        beta.min(MATE - ply as i32 - 1),
+    )
+}
+
+/// Score `quality` found at ply `ply` as stored in the
+/// transposition table, where mates count from the node itself.
+fn score_to_tt(quality: i32, ply: usize) -> i32 {
+    if quality >= MATE_BOUND {
+        quality + ply as i32
+    } else if quality <= -MATE_BOUND {
+        quality - ply as i32
+    } else {
+        quality
+    }
+}
+
+/// Score `quality` read from the transposition table at ply `ply`.
+fn score_from_tt(quality: i32, ply: usize) -> i32 {
+    if quality >= MATE_BOUND {
+        quality - ply as i32
+    } else if quality <= -MATE_BOUND {
+        quality + ply as i32
+    } else {
+        quality
+    }
+}
+
+/// Result stored in the transposition table which
+/// settles the node searched with window `alpha`..`beta`.
+fn tt_cutoff(
+    board: &Board,
+    depth: u8,
+    ply: usize,
+    alpha: i32,
+    beta: i32,
+    tt: &TranspositionTable,
+) -> Option<(Option<Move>, i32)> {
+    let entry = tt.probe(board.hash())?;
+    if entry.depth < depth {
+        return None;
+    }
+
+    let score = score_from_tt(entry.score, ply);
+    match entry.bound {
+        Bound::Exact => Some((entry.best_move, score)),
+        Bound::Lower if score >= beta => Some((entry.best_move, score)),
+        Bound::Upper if score <= alpha => Some((entry.best_move, score)),
+        _ => None,
+    }
+}
+
+/// Kind of bound a fail-soft `quality` is
+/// for a node searched with window (`alpha`, `beta`).
+fn bound(quality: i32, alpha: i32, beta: i32) -> Bound {
+    // Przy fail-soft wynik poza oknem jest tylko
+    // ograniczeniem prawdziwej wartości.
+    if quality <= alpha {
+        Bound::Upper
+    } else if quality >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
+
+fn tt_store(
+    board: &Board,
+    depth: u8,
+    ply: usize,
+    bound: Bound,
+    best_move: (Option<Move>, i32),
+    tt: &TranspositionTable,
+) {
+    tt.store(
+        board.hash(),
+        Entry {
+            depth,
+            score: score_to_tt(best_move.1, ply),
+            bound,
+            best_move: best_move.0,
+        },
+    );
+}
+
+/// Result of searching one move of a node in parallel.
+#[derive(Clone, Copy)]
+struct RatedMove {
+    index: usize,
+    r#move: Move,
+    quality: i32,
+    /// Whether `quality` beat the alpha the move was searched with.
+    improved: bool,
+}
+
+impl RatedMove {
+    /// Better of two results. Moves which beat their alpha win
+    /// over those that did not, then higher quality, then the move
+    /// which comes earlier in the ordering.
+    fn better(self, other: Self) -> Self {
+        let key = |rated: &Self| (rated.improved, rated.quality, Reverse(rated.index));
+        if key(&other) > key(&self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+fn alpha_beta_multithreaded(
+    board: &mut Board,
+    depth: u8,
+    ply: usize,
+    alpha: i32,
+    beta: i32,
+    context: &Context,
+) -> (Option<Move>, i32) {
+    // Forced stop.
+    if stopped() {
+        return (None, 0);
+    }
+
+    if depth < 4 {
+        return alpha_beta(board, depth, ply, alpha, beta, context);
+    }
+
+    // Mate distance pruning.
+    let (alpha, beta) = mate_distance_window(ply, alpha, beta);
+    if ply > 0 && alpha >= beta {
+        return (None, alpha);
+    }
+
+    if is_draw(board, ply) {
+        return (None, DRAW);
+    }
+
+    if let Some(best_move) = tt_cutoff(board, depth, ply, alpha, beta, context.tt) {
+        return best_move;
+    }
+
+    if let Some(quality) = null_move_cutoff(board, depth, ply, beta, context) {
+        return (None, quality);
+    }
+
+    let singular = singular_move(board, depth, ply, context);
+    let moves_to_consider: Vec<Move> = context.move_picker(board, ply).moves(board).collect();
+    if moves_to_consider.is_empty() {
+        return (None, no_moves_score(board, ply));
+    }
+
+    // Pierwszy ruch liczymy sami, żeby pozostałe wątki
+    // zaczynały już z rozsądnym oknem.
+    let first = moves_to_consider[0];
+    let quality = principal_variation_search(
+        board,
+        &first,
+        0,
+        singular == Some(first),
+        depth,
+        ply,
+        alpha,
+        beta,
+        None,
+        context,
+    )
+    .expect("only futility pruning skips a move");
+
+    // Ruch może zastąpić najlepszy tylko, jeśli pobił alfę,
+    // z którą był liczony. Inaczej jego wynik jest tylko
+    // górnym ograniczeniem.
+    let mut best = RatedMove {
+        index: 0,
+        r#move: first,
+        quality,
+        improved: quality > alpha,
+    };
+    if quality < beta {
+        let shared_alpha = AtomicI32::new(alpha.max(quality));
+        let board: &Board = board;
+
+        let rest = moves_to_consider
+            .par_iter()
+            .enumerate()
+            .skip(1)
+            // Planszę kopiujemy raz na porcję pracy wątku,
+            // a nie dla każdego ruchu. Potem tylko wykonujemy
+            // i cofamy na niej ruchy.
+            .map_init(
+                || board.clone(),
+                |cloned_board, (index, r#move)| {
+                    let alpha = shared_alpha.load(Ordering::SeqCst);
+
+                    // Forced stop albo odcięcie znalezione przez inny wątek.
+                    if stopped() || alpha >= beta {
+                        return None;
+                    }
+
+                    let quality = principal_variation_search(
+                        cloned_board,
+                        r#move,
+                        index,
+                        singular == Some(*r#move),
+                        depth,
+                        ply,
+                        alpha,
+                        beta,
+                        None,
+                        context,
+                    )?;
+                    shared_alpha.fetch_max(quality, Ordering::SeqCst);
+
+                    Some(RatedMove {
+                        index,
+                        r#move: *r#move,
+                        quality,
+                        improved: quality > alpha,
+                    })
+                },
+            )
+            .flatten()
+            .reduce_with(RatedMove::better);
+
+        if let Some(rated_move) = rest {
+            best = best.better(rated_move);
+        }
+    }
+    let improved = best.improved;
+    let best_move = (Some(best.r#move), best.quality);
+
+    // Forced stop.
+    if stopped() {
+        return (None, 0);
+    }
+
+    if let (Some(r#move), quality) = best_move {
+        if quality >= beta {
+            context.store_cutoff(board, &r#move, depth, ply);
+        }
+    }
+
+    let bound = if improved {
+        bound(best_move.1, alpha, beta)
+    } else {
+        Bound::Upper
+    };
+    tt_store(board, depth, ply, bound, best_move, context.tt);
+
+    best_move
+}
+
+fn alpha_beta(
+    board: &mut Board,
+    depth: u8,
+    ply: usize,
+    alpha: i32,
+    beta: i32,
+    context: &Context,
+) -> (Option<Move>, i32) {
+    // Forced stop.
+    if stopped() {
+        return (None, 0);
+    }
+
+    // Mate distance pruning.
+    let (alpha, beta) = mate_distance_window(ply, alpha, beta);
+    if ply > 0 && alpha >= beta {
+        return (None, alpha);
+    }
+
+    if is_draw(board, ply) {
+        return (None, DRAW);
+    }
+
+    if depth == 0 {
+        return (None, quiescence(board, ply, alpha, beta, context));
+    }
+
+    if let Some(best_move) = tt_cutoff(board, depth, ply, alpha, beta, context.tt) {
+        return best_move;
+    }
+
+    let in_check = is_check(board, &board.which_turn());
+    let static_eval = eval_relative(board);
+    let futile = context.options.futility_pruning
+        && ply > 0
+        && depth <= FUTILITY_MAX_DEPTH
+        && !in_check
+        && alpha.abs() < MATE / 2
+        && beta.abs() < MATE / 2;
+
+    // Reverse futility pruning: przewaga jest tak duża,
+    // że przeciwnik nie zdąży jej odrobić.
+    if futile && static_eval - FUTILITY_MARGIN * depth as i32 >= beta {
+        return (None, static_eval);
+    }
+
+    if let Some(quality) = null_move_cutoff(board, depth, ply, beta, context) {
+        return (None, quality);
+    }
+
+    let mut best_move: (Option<Move>, i32) = (None, -INFINITY);
+    let mut window_alpha = alpha;
+    let mut moves_to_consider = context.move_picker(board, ply);
+    for i in 0.. {
+        // Po każdym ruchu plansza wraca do tej samej pozycji.
+        let r#move = match moves_to_consider.next_move(board) {
+            Some(r#move) => r#move,
+            None => break,
+        };
+        let quality = match principal_variation_search(
+            board,
+            &r#move,
+            i,
+            false,
+            depth,
+            ply,
+            window_alpha,
+            beta,
+            futile.then_some(static_eval),
+            context,
+        ) {
+            Some(quality) => quality,
+            None => continue,
+        };
+
+        if quality > best_move.1 {
+            best_move = (Some(r#move), quality);
+
+            if quality > window_alpha {
+                window_alpha = quality;
+            }
+
+            // Przeciwnik nie dopuści do tej pozycji.
+            if window_alpha >= beta {
+                context.store_cutoff(board, &r#move, depth, ply);
+                break;
+            }
+        }
+    }
+
+    if best_move.0.is_none() {
+        return (None, no_moves_score(board, ply));
+    }
+
+    // Forced stop.
+    if stopped() {
+        return (None, 0);
+    }
+
+    tt_store(
+        board,
+        depth,
+        ply,
+        bound(best_move.1, alpha, beta),
+        best_move,
+        context.tt,
+    );
+
+    best_move
+}
+
+/// Is position `board` at ply `ply` drawn.
+///
+/// Below the root already the first repetition counts as a draw,
+/// since the player who repeated once can just as well repeat again.
+fn is_draw(board: &Board, ply: usize) -> bool {
+    ply > 0 && (board.repetitions() > 0 || board.is_draw())
+}
+
+/// Score of position `board` at ply `ply`
+/// where the player on move has no legal moves.
+fn no_moves_score(board: &Board, ply: usize) -> i32 {
+    if is_check(board, &board.which_turn()) {
+        // Check Mate
+        -MATE + ply as i32
+    } else {
+        // Pat
+        DRAW
+    }
+}
+
+/// Search captures and promotions until the position is quiet,
+/// so that the evaluation never stops in the middle of an exchange.
+fn quiescence(board: &mut Board, ply: usize, alpha: i32, beta: i32, context: &Context) -> i32 {
+    // Forced stop.
+    if stopped() {
+        return 0;
+    }
+
+    // Przy szachu nie można "stać w miejscu",
+    // więc przeszukujemy wszystkie obrony.
+    // Jeśli żadnej nie ma, to jest mat.
+    let in_check = is_check(board, &board.which_turn());
+    let (stand_pat, mut moves_to_consider) = if in_check {
+        (
+            -MATE + ply as i32,
+            MovePicker::new(None, [None, None], &context.history),
+        )
+    } else {
+        (eval_relative(board), MovePicker::captures(&context.history))
+    };
+
+    if stand_pat >= beta {
+        return stand_pat;
+    }
+
+    let mut best_quality = stand_pat;
+    let mut window_alpha = alpha.max(stand_pat);
+    while let Some(r#move) = moves_to_consider.next_move(board) {
+        // Delta pruning: nawet zbicie tej figury
+        // nie podniesie wyniku do alfy.
+        if let Some(captured) = captured_kind(board, &r#move) {
+            if !in_check
+                && r#move.promotion().is_none()
+                && stand_pat + kind_value(&captured) + DELTA_MARGIN <= window_alpha
+            {
+                continue;
+            }
+        }
+
+        let undo = board.make_move(&r#move);
+        let quality = -quiescence(board, ply + 1, -beta, -window_alpha, context);
+        board.unmake_move(&r#move, undo);
+
+        if quality > best_quality {
+            best_quality = quality;
+
+            if quality > window_alpha {
+                window_alpha = quality;
+            }
+
+            if window_alpha >= beta {
+                break;
+            }
+        }
+    }
+
+    best_quality
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::FENString;
+    use super::*;
+
+    #[test]
+    fn test_alpha_beta() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+
+        // Mat w jednym ruchu: Ra8#.
+        let mut board =
+            Board::try_from(FENString::try_from("6k1/5ppp/8/8/8/8/8/R5K1 w - -").unwrap()).unwrap();
+        let tt = TranspositionTable::new(1);
+        let context = Context::new(&tt, &SearchOptions::default());
+
+        let (best_move, quality) = alpha_beta(&mut board, 3, 0, -INFINITY, INFINITY, &context);
+        assert_eq!(best_move, Some(Move::try_from("a1a8").unwrap()));
+        assert_eq!(quality, MATE - 1);
+
+        // Ten sam wynik przy wąskim oknie aspiracyjnym wokół
+        // złej oceny: okno musi się poszerzyć w górę.
+        let tt = TranspositionTable::new(1);
+        assert_eq!(
+            aspiration_search(
+                &mut board,
+                ASPIRATION_MIN_DEPTH,
+                0,
+                &Context::new(&tt, &SearchOptions::default())
+            ),
+            Some((Move::try_from("a1a8").unwrap(), MATE - 1))
+        );
+        assert_eq!(
+            principal_variation(&board, ASPIRATION_MIN_DEPTH, &tt),
+            vec![Move::try_from("a1a8").unwrap()]
+        );
+
+        // Czarne mają tylko Kg8, po którym Ra8#:
+        // okno musi się poszerzyć w dół.
+        let mut board =
+            Board::try_from(FENString::try_from("7k/8/6K1/8/8/8/8/R7 b - -").unwrap()).unwrap();
+        let tt = TranspositionTable::new(1);
+        assert_eq!(
+            aspiration_search(
+                &mut board,
+                ASPIRATION_MIN_DEPTH,
+                0,
+                &Context::new(&tt, &SearchOptions::default())
+            ),
+            Some((Move::try_from("h8g8").unwrap(), -(MATE - 2)))
+        );
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+        let tt = TranspositionTable::new(1);
+        let context = Context::new(&tt, &SearchOptions::default());
+
+        // Pat: czarny król nie ma ruchu, ale nie jest szachowany.
+        let mut board =
+            Board::try_from(FENString::try_from("k7/8/1Q6/8/8/8/8/7K b - -").unwrap()).unwrap();
+        assert_eq!(
+            alpha_beta(&mut board, 2, 0, -INFINITY, INFINITY, &context),
+            (None, DRAW)
+        );
+
+        // Mat w dwóch ruchach: Ra7 Kd8, Rb8#.
+        let mut board =
+            Board::try_from(FENString::try_from("2k5/8/8/8/8/8/R7/1R4K1 w - -").unwrap()).unwrap();
+        let (_, quality) = alpha_beta(&mut board, 4, 0, -INFINITY, INFINITY, &context);
+        assert_eq!(quality, MATE - 3);
+
+        assert_eq!(uci_score(MATE - 1), "mate 1");
+        assert_eq!(uci_score(MATE - 3), "mate 2");
+        assert_eq!(uci_score(-MATE + 2), "mate -1");
+        assert_eq!(uci_score(-25), "cp -25");
+    }
+
+    #[test]
+    fn test_no_legal_moves() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+        let tt = TranspositionTable::new(1);
+        let options = SearchOptions::default();
+
+        // Mat szewski i pat: nie ma czego zagrać.
+        for fen in [
+            "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4",
+            "k7/8/1Q6/8/8/8/8/7K b - -",
+        ] {
+            let board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+            assert_eq!(get_move(&board, &tt, &options), None, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_quiescence() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+
+        // Pion d5 jest broniony przez e6, więc Hxd5 oddaje hetmana.
+        let mut board =
+            Board::try_from(FENString::try_from("4k3/8/4p3/3p4/8/8/8/3QK3 w - -").unwrap())
+                .unwrap();
+        let tt = TranspositionTable::new(1);
+        let context = Context::new(&tt, &SearchOptions::default());
+
+        let (best_move, quality) = alpha_beta(&mut board, 1, 0, -INFINITY, INFINITY, &context);
+        assert_ne!(best_move, Some(Move::try_from("d1d5").unwrap()));
+        assert!(quality > 0);
+    }
+
+    #[test]
+    fn test_late_move_reductions() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+
+        // Goniec na a3 wisi, a skracane ciche ruchy
+        // nie mogą dostać lepszej oceny niż jego bicie.
+        let mut board = Board::new();
+        for r#move in [
+            "e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1", "f8a3",
+        ] {
+            board.try_play(r#move, false).unwrap();
+        }
+        let captures = [
+            Move::try_from("b1a3").unwrap(),
+            Move::try_from("b2a3").unwrap(),
+        ];
+
+        let tt = TranspositionTable::new(16);
+        let context = Context::new(&tt, &SearchOptions::default());
+        let mut quality = 0;
+        for depth in 2..=7 {
+            let (best_move, score) =
+                aspiration_search(&mut board, depth, quality, &context).unwrap();
+            assert!(
+                captures.contains(&best_move),
+                "depth {}: {}",
+                depth,
+                best_move
+            );
+            assert!(score >= 200, "depth {}: {}", depth, score);
+            quality = score;
+        }
+    }
+
+    #[test]
+    fn test_extended_hanging_piece() {
+        STOP_ALL_THREADS.store(false, Ordering::SeqCst);
+
+        // Po 4...Ga3 czarne oddają gońca za piona. Przedłużenia
+        // nie mogą sprawić, że ten ruch wygląda na dobry.
+        let mut board = Board::new();
+        for r#move in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"] {
+            board.try_play(r#move, false).unwrap();
+        }
+        let blunder = Move::try_from("f8a3").unwrap();
+
+        let tt = TranspositionTable::new(16);
+        let options = SearchOptions {
+            null_move: false,
+            late_move_reductions: false,
+            futility_pruning: false,
+            ..SearchOptions::default()
+        };
+        let context = Context::new(&tt, &options);
+        let mut quality = 0;
+        for depth in 2..=7 {
+            let (best_move, score) =
+                aspiration_search(&mut board, depth, quality, &context).unwrap();
+            assert_ne!(best_move, blunder, "depth {}", depth);
+            quality = score;
+        }
+    }
+
+    #[test]
+    fn test_extension() {
+        let tt = TranspositionTable::new(1);
+        let mut options = SearchOptions::default();
+        let mut board =
+            Board::try_from(FENString::try_from("8/8/4P3/8/8/8/k7/4K3 w - -").unwrap()).unwrap();
+
+        let push = Move::try_from("e6e7").unwrap();
+        board.make_move(&push);
+        assert_eq!(
+            Context::new(&tt, &options).extension(&board, &push, false, false, 1),
+            1
+        );
+
+        options.seventh_rank_extension = false;
+        assert_eq!(
+            Context::new(&tt, &options).extension(&board, &push, false, false, 1),
+            0
+        );
+        assert_eq!(
+            Context::new(&tt, &options).extension(&board, &push, true, false, 1),
+            1
+        );
+        assert_eq!(
+            Context::new(&tt, &options).extension(&board, &push, true, false, MAX_EXTENSION_PLY),
+            0
+        );
+    }
+}
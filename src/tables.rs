@@ -85,6 +85,11 @@ static FIELD_VALUES: [[[i32; 8]; 8]; 7] = [
     ],
 ];
 
+/// Material value of a piece of kind `kind_of_piece`.
+pub fn kind_value(kind_of_piece: &KindOfPiece) -> i32 {
+    PIECE_VALUES[kind_of_piece.index()]
+}
+
 pub fn piece_value(field: &Field, piece: &Piece, early_stage: bool) -> i32 {
     let (c1, c2) = match piece.color {
         Color::Black => (
@@ -135,7 +140,7 @@ mod test {
             for j in 1..=8 {
                 let field = Field::build_unchecked(i, j);
                 let value = match board.field_content(&field) {
-                    Some(piece) => piece_value(&field, &piece, true),
+                    Some(piece) => piece_value(&field, piece, true),
                     None => 0,
                 };
 
@@ -156,7 +161,7 @@ mod test {
             for j in 1..=8 {
                 let field = Field::build_unchecked(i, j);
                 let value = match board.field_content(&field) {
-                    Some(piece) => piece_value(&field, &piece, true),
+                    Some(piece) => piece_value(&field, piece, true),
                     None => 0,
                 };
 
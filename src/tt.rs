@@ -0,0 +1,156 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use super::book::Move;
+
+/// Default size of the transposition table in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Largest size of the transposition table in megabytes.
+pub const MAX_HASH_MB: usize = 4096;
+
+/// How the stored score relates to the real one.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The real score is at least the stored one.
+    Lower,
+    /// The real score is at most the stored one.
+    Upper,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+impl Entry {
+    fn pack(&self) -> u64 {
+        let r#move = match self.best_move {
+            Some(r#move) => r#move.code() as u64,
+            None => 0,
+        };
+        let bound: u64 = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        r#move | (self.depth as u64) << 16 | bound << 24 | (self.score as u32 as u64) << 32
+    }
+
+    fn unpack(data: u64) -> Option<Self> {
+        let best_move = Move::from_code(data as u16);
+        let bound = match (data >> 24) & 0xFF {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            2 => Bound::Upper,
+            _ => return None,
+        };
+
+        Some(Entry {
+            depth: ((data >> 16) & 0xFF) as u8,
+            score: (data >> 32) as u32 as i32,
+            bound,
+            best_move,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed-size hash table of searched positions
+/// shared by all search threads.
+///
+/// Slots are written without locks. The key is stored
+/// xored with the data, so a slot torn by two threads writing
+/// at once fails the key check instead of returning garbage.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    /// Table taking about `megabytes` megabytes of memory.
+    pub fn new(megabytes: usize) -> Self {
+        let count = megabytes.clamp(1, MAX_HASH_MB) * 1024 * 1024 / std::mem::size_of::<Slot>();
+        let mut slots = Vec::with_capacity(count);
+        slots.resize_with(count, Slot::default);
+
+        TranspositionTable { slots }
+    }
+
+    /// Forget all stored positions.
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = Slot::default();
+        }
+    }
+
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    /// Entry stored for position with hash `key`, if any.
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if slot.key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+
+        Entry::unpack(data)
+    }
+
+    /// Remember entry `entry` for position with hash `key`.
+    pub fn store(&self, key: u64, entry: Entry) {
+        let slot = self.slot(key);
+
+        // Nie nadpisujemy głębszego wyniku dla tej samej
+        // pozycji, chyba że nowy wynik jest dokładny.
+        if let Some(old) = self.probe(key) {
+            if old.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+
+        let data = entry.pack();
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_store_probe() {
+        let mut tt = TranspositionTable::new(1);
+        let entry = Entry {
+            depth: 7,
+            score: -1234,
+            bound: Bound::Lower,
+            best_move: Some(Move::try_from("e7e8n").unwrap()),
+        };
+
+        assert_eq!(tt.probe(0xDEADBEEF), None);
+        tt.store(0xDEADBEEF, entry);
+        assert_eq!(tt.probe(0xDEADBEEF), Some(entry));
+
+        // Płytszy wynik nie wypiera głębszego.
+        tt.store(0xDEADBEEF, Entry { depth: 3, ..entry });
+        assert_eq!(tt.probe(0xDEADBEEF), Some(entry));
+
+        tt.clear();
+        assert_eq!(tt.probe(0xDEADBEEF), None);
+    }
+}
@@ -0,0 +1,111 @@
+use vendace::board::Board;
+use vendace::board::FENString;
+use vendace::perft::divide;
+use vendace::perft::perft;
+
+/// Perft of position `fen` for every depth from 1,
+/// compared with the known node counts `expected`.
+fn check(fen: &str, expected: &[u64]) {
+    let mut board = Board::try_from(FENString::try_from(fen).unwrap()).unwrap();
+    let before = board.clone();
+
+    for (depth, nodes) in expected.iter().enumerate() {
+        assert_eq!(
+            perft(&mut board, depth as u8 + 1),
+            *nodes,
+            "depth {}",
+            depth + 1
+        );
+    }
+
+    assert_eq!(board, before);
+}
+
+#[test]
+fn perft_start_position() {
+    check(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902, 197281],
+    );
+}
+
+#[test]
+fn perft_kiwipete() {
+    check(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97862],
+    );
+}
+
+#[test]
+fn perft_position_3() {
+    check(
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2812, 43238],
+    );
+}
+
+#[test]
+fn perft_position_4() {
+    check(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467],
+    );
+}
+
+#[test]
+fn perft_position_5() {
+    check(
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1486, 62379],
+    );
+}
+
+#[test]
+fn perft_position_6() {
+    check(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2079, 89890],
+    );
+}
+
+#[test]
+fn perft_chess960() {
+    check(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12189],
+    );
+    check(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18002],
+    );
+    check(
+        "1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9",
+        &[29, 502, 14569],
+    );
+}
+
+#[test]
+fn divide_start_position() {
+    let mut board = Board::new();
+    let (moves, total) = divide(&mut board, 3);
+
+    assert_eq!(moves.len(), 20);
+    assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+    assert_eq!(total, 8902);
+    for (r#move, nodes) in moves {
+        match r#move.to_string().as_str() {
+            "e2e4" => assert_eq!(nodes, 600),
+            "g1f3" => assert_eq!(nodes, 440),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn depth_zero() {
+    // Sama pozycja to jeden węzeł.
+    let mut board = Board::new();
+    assert_eq!(perft(&mut board, 0), 1);
+    assert_eq!(divide(&mut board, 0), (Vec::new(), 1));
+}